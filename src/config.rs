@@ -4,6 +4,7 @@ use figment::{
     Error, Figment,
 };
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
 
 #[derive(Deserialize)]
@@ -12,15 +13,31 @@ struct Expected {
     deployments: Vec<String>,
 }
 
+/// Limits past which an otherwise healthy resource is reported as suspicious
+#[derive(Deserialize, Clone, Default)]
+pub struct Thresholds {
+    /// Total container restarts a pod may have before it is flagged
+    pub max_restarts: Option<i32>,
+    /// Only flag restarts if the most recent one happened within this many seconds
+    pub restart_window: Option<i64>,
+}
+
 #[derive(Deserialize)]
 struct Kubernetes {
     expected: Expected,
+    #[serde(default)]
+    thresholds: Thresholds,
+    #[serde(default)]
+    compare_contexts: Vec<String>,
 }
 
 #[derive(Deserialize)]
 struct Switchboard {
     links: Vec<Link>,
     actions: Vec<Action>,
+    /// Values for `{name}` placeholders in links and actions
+    #[serde(default)]
+    vars: HashMap<String, String>,
 }
 
 #[derive(Deserialize)]
@@ -38,6 +55,14 @@ impl Config {
         self.kubernetes.expected.deployments.clone()
     }
 
+    pub fn compare_contexts(&self) -> Vec<String> {
+        self.kubernetes.compare_contexts.clone()
+    }
+
+    pub fn thresholds(&self) -> Thresholds {
+        self.kubernetes.thresholds.clone()
+    }
+
     pub fn links(&self) -> Vec<Link> {
         self.switchboard.links.clone()
     }
@@ -45,6 +70,10 @@ impl Config {
     pub fn actions(&self) -> Vec<Action> {
         self.switchboard.actions.clone()
     }
+
+    pub fn vars(&self) -> HashMap<String, String> {
+        self.switchboard.vars.clone()
+    }
 }
 
 pub fn load() -> Result<Config, Error> {
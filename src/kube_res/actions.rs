@@ -1,78 +1,628 @@
-use super::ActionResult;
+use super::channel::MessageSender;
+use super::client::ClientManager;
+use super::deployments::{deployment_selector, rollout_status, Rollout};
+use super::exec::run_command;
+use super::pods::is_ready;
+use super::port_forward::{self, resolve_target};
+use super::secrets::SecretValue;
+use super::{describe_error, ActionOutcome, ActionResult, Error, KubeError};
 use crate::welcome::Action;
 use crate::KubeMessage;
-use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::{ConfigMap, Pod, Secret};
+use k8s_openapi::chrono::Utc;
 use kube::{
-    api::Api,
-    {Client, Error},
+    api::{Api, DeleteParams, ListParams, Patch, PatchParams},
+    Client,
 };
-use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
 
-pub fn run_action(namespace: String, action: Action, tx: Sender<KubeMessage>) {
+// How often a rollout is checked while waiting for it to finish
+const ROLLOUT_POLL: Duration = Duration::from_secs(2);
+// Kubernetes' default for `spec.progressDeadlineSeconds`
+const DEFAULT_PROGRESS_DEADLINE: u64 = 600;
+// How long to wait for deleted pods to be replaced by ready ones
+const REPLACEMENT_TIMEOUT: Duration = Duration::from_secs(300);
+// How long an `exec` command can run before it is given up on
+const EXEC_TIMEOUT: Duration = Duration::from_secs(300);
+
+pub fn run_action(clients: ClientManager, namespace: String, action: Action, tx: MessageSender) {
     tokio::spawn(async move {
-        match Client::try_default().await {
+        match clients.client().await {
             Ok(client) => {
                 run_valid_action(client, tx, namespace, action).await;
             }
-            Err(err) => println!("Could not get client, error: {}", err),
+            Err(err) => {
+                if let Err(e) = tx.send(error(action.name, err)) {
+                    println!("Failed running action: {}", e);
+                }
+            }
         };
     });
 }
 
-async fn run_valid_action(
+async fn run_valid_action(client: Client, tx: MessageSender, namespace: String, action: Action) {
+    let msg = match action.action.as_str() {
+        "get-secret" => get_secret(client, &namespace, action).await,
+        "get-configmap" => get_configmap(client, &namespace, action).await,
+        "restart-deployment" => restart_deployment(client, &namespace, action, &tx).await,
+        "scale-deployment" => scale_deployment(client, &namespace, action).await,
+        "delete-pod" if action.confirmed.is_empty() => {
+            matching_pods(client, &namespace, action).await
+        }
+        "delete-pod" => delete_pods(client, &namespace, action, &tx).await,
+        "port-forward" => forward_port(client, &namespace, action, &tx).await,
+        "exec" => exec_command(client, &namespace, action).await,
+        other => failed(action.name, format!("Unknown action type: {}", other)),
+    };
+    if let Err(e) = tx.send(msg) {
+        println!("Failed running action: {}", e);
+    }
+}
+
+async fn get_secret(client: Client, namespace: &str, action: Action) -> KubeMessage {
+    let secrets_request: Api<Secret> = Api::namespaced(client, namespace);
+    let secret = secrets_request.get(action.resource.as_str()).await;
+    match secret {
+        Ok(s) => match s.data {
+            Some(data) => {
+                let values: Vec<SecretValue> = data
+                    .iter()
+                    .map(|(key, value)| SecretValue::new(key, &value.0))
+                    .collect();
+                let keys: Vec<String> = values.iter().map(|v| v.key.clone()).collect();
+                KubeMessage::Action(Ok(ActionResult {
+                    name: action.name,
+                    results: keys.join("\n"),
+                    outcome: ActionOutcome::Done,
+                    secrets: values,
+                    output: None,
+                    pods: None,
+                }))
+            }
+            None => success(action.name, "Nothing found!".to_owned()),
+        },
+        Err(err) => error(action.name, err),
+    }
+}
+
+async fn get_configmap(client: Client, namespace: &str, action: Action) -> KubeMessage {
+    let configmaps: Api<ConfigMap> = Api::namespaced(client, namespace);
+    match configmaps.get(action.resource.as_str()).await {
+        Ok(cm) => {
+            let lines = configmap_lines(&cm);
+            if lines.is_empty() {
+                success(action.name, "Nothing found!".to_owned())
+            } else {
+                success(action.name, lines.join("\n"))
+            }
+        }
+        Err(err) => error(action.name, err),
+    }
+}
+
+/// `key: value` lines for a ConfigMap, with only the size shown for binary data
+fn configmap_lines(cm: &ConfigMap) -> Vec<String> {
+    let data = cm
+        .data
+        .iter()
+        .flatten()
+        .map(|(key, value)| format!("{}: {}", key, value));
+    let binary = cm
+        .binary_data
+        .iter()
+        .flatten()
+        .map(|(key, value)| format!("{}: <binary, {} bytes>", key, value.0.len()));
+    data.chain(binary).collect()
+}
+
+/// Does what `kubectl rollout restart` does, then follows the rollout until the new
+/// pods are all available or it runs past the deployment's progress deadline
+async fn restart_deployment(
     client: Client,
-    tx: Sender<KubeMessage>,
-    namespace: String,
+    namespace: &str,
     action: Action,
-) {
-    let validated_action = match action.action.as_str() {
-        "get-secret" => {
-            let secrets_request: Api<Secret> = Api::namespaced(client, namespace.as_str());
-            let secret = secrets_request.get(action.resource.as_str()).await;
-            Some(match secret {
-                Ok(s) => match s.data {
-                    Some(data) => {
-                        let mut responses: Vec<String> = vec![];
-                        for (key, value) in data.iter() {
-                            let parsed_value = match String::from_utf8(value.0.clone()) {
-                                Ok(str_value) => str_value,
-                                _ => "".to_owned(),
-                            };
-                            let line = key.to_owned() + ": " + parsed_value.as_str();
-                            responses.push(line);
-                        }
-                        success(action.name, responses.join("\n"))
-                    }
-                    None => success(action.name, format!("Nothing found!")),
-                },
-                Err(err) => error(action.name, err),
-            })
-        }
-        _ => None,
+    tx: &MessageSender,
+) -> KubeMessage {
+    let deploys: Api<Deployment> = Api::namespaced(client, namespace);
+    let patch = serde_json::json!({
+        "spec": {"template": {"metadata": {"annotations": {
+            "kubectl.kubernetes.io/restartedAt": Utc::now().to_rfc3339()
+        }}}}
+    });
+    let restarted = deploys
+        .patch(
+            &action.resource,
+            &PatchParams::default(),
+            &Patch::Merge(&patch),
+        )
+        .await;
+    let deadline = match restarted {
+        Ok(deploy) => deploy
+            .spec
+            .and_then(|s| s.progress_deadline_seconds)
+            .map(|s| s as u64)
+            .unwrap_or(DEFAULT_PROGRESS_DEADLINE),
+        Err(err) => return error(action.name, err),
     };
-    match validated_action {
-        Some(msg) => {
-            match tx.send(msg) {
-                Ok(_) => {}
-                Err(e) => println!("Failed running action: {}", e),
-            };
+
+    let started = Instant::now();
+    loop {
+        let deploy = match deploys.get(&action.resource).await {
+            Ok(deploy) => deploy,
+            Err(err) => return error(action.name, err),
+        };
+        match rollout_status(&deploy) {
+            Rollout::Complete(msg) => return success(action.name, msg),
+            Rollout::Failed(msg) => return failed(action.name, msg),
+            Rollout::Progressing(msg) if started.elapsed().as_secs() > deadline => {
+                let msg = format!("Timed out after {}s: {}", deadline, msg);
+                return failed(action.name, msg);
+            }
+            Rollout::Progressing(msg) => {
+                let _ = tx.send(progress(action.name.clone(), msg));
+            }
+        }
+        tokio::time::sleep(ROLLOUT_POLL).await;
+    }
+}
+
+/// Sets the replicas of a deployment through its scale subresource
+async fn scale_deployment(client: Client, namespace: &str, action: Action) -> KubeMessage {
+    let replicas = match action.replicas {
+        Some(replicas) => replicas,
+        None => return failed(action.name, "No replica count given".to_owned()),
+    };
+    let deploys: Api<Deployment> = Api::namespaced(client, namespace);
+    let before = match deploys.get_scale(&action.resource).await {
+        Ok(scale) => scale.spec.and_then(|s| s.replicas).unwrap_or(0),
+        Err(err) => return error(action.name, err),
+    };
+    let patch = serde_json::json!({"spec": {"replicas": replicas}});
+    let scaled = deploys
+        .patch_scale(
+            &action.resource,
+            &PatchParams::default(),
+            &Patch::Merge(&patch),
+        )
+        .await;
+    match scaled {
+        Ok(scale) => {
+            let after = scale.spec.and_then(|s| s.replicas).unwrap_or(0);
+            let msg = format!(
+                "Scaled {} from {} to {} replicas",
+                action.resource, before, after
+            );
+            success(action.name, msg)
+        }
+        Err(err) => error(action.name, err),
+    }
+}
+
+/// Lists the pods a `delete-pod` action would delete, for the user to confirm
+async fn matching_pods(client: Client, namespace: &str, action: Action) -> KubeMessage {
+    let pods: Api<Pod> = Api::namespaced(client, namespace);
+    let names = match pod_selection(&action.resource) {
+        PodSelection::Name(name) => pods.get(&name).await.map(|_| vec![name]),
+        PodSelection::Labels(selector) => pods
+            .list(&ListParams::default().labels(&selector))
+            .await
+            .map(|list| {
+                list.items
+                    .into_iter()
+                    .filter_map(|p| p.metadata.name)
+                    .collect()
+            }),
+    };
+    match names {
+        Ok(names) if names.is_empty() => {
+            failed(action.name, format!("No pods match {}", action.resource))
+        }
+        Ok(names) => needs_confirmation(action.name, namespace, names),
+        Err(err) => error(action.name, err),
+    }
+}
+
+/// Deletes the confirmed pods, then waits for whatever controls them to bring up
+/// ready replacements
+async fn delete_pods(
+    client: Client,
+    namespace: &str,
+    action: Action,
+    tx: &MessageSender,
+) -> KubeMessage {
+    let pods: Api<Pod> = Api::namespaced(client, namespace);
+    let mut owners: Vec<String> = vec![];
+    for name in action.confirmed.iter() {
+        match pods.get(name).await {
+            Ok(pod) => owners.extend(owner_uids(&pod)),
+            Err(err) => return error(action.name, err),
         }
-        None => {}
+    }
+    // Pods of the same controllers that are already there, siblings included, are
+    // not replacements however ready they are
+    let existing: Vec<String> = match pods.list(&ListParams::default()).await {
+        Ok(list) => list
+            .items
+            .iter()
+            .filter_map(|pod| pod.metadata.uid.clone())
+            .collect(),
+        Err(err) => return error(action.name, err),
     };
+    for name in action.confirmed.iter() {
+        if let Err(err) = pods.delete(name, &DeleteParams::default()).await {
+            return error(action.name, err);
+        }
+    }
+    let deleted = format!("Deleted {}", action.confirmed.join(", "));
+    if owners.is_empty() {
+        let msg = format!(
+            "{}. They have no controller, so will not be replaced",
+            deleted
+        );
+        return success(action.name, msg);
+    }
+
+    let wanted = action.confirmed.len();
+    let started = Instant::now();
+    loop {
+        let ready = match pods.list(&ListParams::default()).await {
+            Ok(list) => ready_replacements(&list.items, &owners, &existing),
+            Err(err) => return error(action.name, err),
+        };
+        let msg = format!("{}. {} of {} replacements ready", deleted, ready, wanted);
+        if ready >= wanted {
+            return success(action.name, msg);
+        }
+        if started.elapsed() > REPLACEMENT_TIMEOUT {
+            let msg = format!(
+                "Timed out after {}s: {}",
+                REPLACEMENT_TIMEOUT.as_secs(),
+                msg
+            );
+            return failed(action.name, msg);
+        }
+        let _ = tx.send(progress(action.name.clone(), msg));
+        tokio::time::sleep(ROLLOUT_POLL).await;
+    }
 }
 
-// For errors we still return an `Ok` so that we can send messages
-// per individual action
-fn error(action_name: String, err: Error) -> KubeMessage {
+/// Forwards a local port to a pod, or to a pod behind a service. The forward keeps
+/// running after the action is done, until it is stopped in the UI.
+async fn forward_port(
+    client: Client,
+    namespace: &str,
+    action: Action,
+    tx: &MessageSender,
+) -> KubeMessage {
+    let port = match action.port {
+        Some(port) => port,
+        None => return failed(action.name, "No port given".to_owned()),
+    };
+    let target = match resolve_target(client.clone(), namespace, &action.resource, port).await {
+        Ok(target) => target,
+        Err(err) => return failed(action.name, err),
+    };
+    let local_port = action.local_port.unwrap_or(port);
+    match port_forward::start(action.name.clone(), client, namespace, target, local_port).await {
+        Ok(forward) => {
+            let msg = format!(
+                "Forwarding {} to {}:{}",
+                forward.local_addr, forward.target.pod, forward.target.port
+            );
+            let _ = tx.send(KubeMessage::PortForward(forward));
+            success(action.name, msg)
+        }
+        Err(err) => failed(action.name, err),
+    }
+}
+
+/// Runs the action's command in a ready pod of `resource` and reports what it printed
+async fn exec_command(client: Client, namespace: &str, action: Action) -> KubeMessage {
+    if action.command.is_empty() {
+        return failed(action.name, "No command given".to_owned());
+    }
+    let pod = match ready_pod(client.clone(), namespace, &action.resource).await {
+        Ok(pod) => pod,
+        Err(err) => return failed(action.name, err),
+    };
+    let run = run_command(
+        client,
+        namespace,
+        &pod,
+        action.container.as_deref(),
+        &action.command,
+    );
+    let output = match tokio::time::timeout(EXEC_TIMEOUT, run).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(err)) => return error(action.name, err),
+        Err(_) => {
+            let msg = format!("Timed out after {}s in {}", EXEC_TIMEOUT.as_secs(), pod);
+            return failed(action.name, msg);
+        }
+    };
+    let (results, outcome) = match output.exit_code {
+        Some(0) => (format!("Ran in {}", pod), ActionOutcome::Done),
+        Some(code) => (
+            format!("Exited with {} in {}", code, pod),
+            ActionOutcome::Failed,
+        ),
+        None => (
+            format!("Ran in {}, exit status unknown", pod),
+            ActionOutcome::Failed,
+        ),
+    };
     KubeMessage::Action(Ok(ActionResult {
-        name: action_name,
-        results: format!("Failed to run action: {}", err),
+        name: action.name,
+        results,
+        outcome,
+        secrets: vec![],
+        output: Some(output),
+        pods: None,
     }))
 }
 
+#[derive(PartialEq, Debug)]
+enum ExecTarget {
+    Pod(String),
+    Labels(String),
+    Deployment(String),
+}
+
+/// `exec` takes a deployment name, a label selector, or `pod/<name>` for one pod
+fn exec_target(resource: &str) -> ExecTarget {
+    match pod_selection(resource) {
+        PodSelection::Labels(selector) => ExecTarget::Labels(selector),
+        PodSelection::Name(name) => match name.strip_prefix("pod/") {
+            Some(pod) => ExecTarget::Pod(pod.to_owned()),
+            None => ExecTarget::Deployment(name),
+        },
+    }
+}
+
+/// The name of a ready pod to run a command in
+async fn ready_pod(client: Client, namespace: &str, resource: &str) -> Result<String, String> {
+    let selector = match exec_target(resource) {
+        ExecTarget::Pod(pod) => return Ok(pod),
+        ExecTarget::Labels(selector) => selector,
+        ExecTarget::Deployment(name) => deployment_selector(client.clone(), namespace, &name)
+            .await
+            .map_err(|e| describe_error(&e))?
+            .ok_or(format!("{} does not select any pods", resource))?,
+    };
+    let pods: Api<Pod> = Api::namespaced(client, namespace);
+    pods.list(&ListParams::default().labels(&selector))
+        .await
+        .map_err(|e| describe_error(&e))?
+        .items
+        .into_iter()
+        .find(is_ready)
+        .and_then(|pod| pod.metadata.name)
+        .ok_or(format!("No ready pods for {}", resource))
+}
+
+#[derive(PartialEq, Debug)]
+enum PodSelection {
+    Name(String),
+    Labels(String),
+}
+
+/// `delete-pod` takes either a pod name or a label selector like `app=web`
+fn pod_selection(resource: &str) -> PodSelection {
+    let resource = resource.trim();
+    let is_selector = resource.contains('=')
+        || resource.contains(',')
+        || resource.contains(' ')
+        || resource.starts_with('!');
+    if is_selector {
+        PodSelection::Labels(resource.to_owned())
+    } else {
+        PodSelection::Name(resource.to_owned())
+    }
+}
+
+fn owner_uids(pod: &Pod) -> Vec<String> {
+    pod.metadata
+        .owner_references
+        .iter()
+        .flatten()
+        .map(|owner| owner.uid.clone())
+        .collect()
+}
+
+/// Counts the ready pods that share an owner with the deleted ones, without being one
+fn ready_replacements(pods: &[Pod], owners: &[String], existing: &[String]) -> usize {
+    pods.iter()
+        .filter(|pod| {
+            let uid = pod.metadata.uid.clone().unwrap_or_default();
+            !existing.contains(&uid)
+                && pod.metadata.deletion_timestamp.is_none()
+                && owner_uids(pod).iter().any(|uid| owners.contains(uid))
+                && is_ready(pod)
+        })
+        .count()
+}
+
+fn error(action_name: String, err: impl Into<Error>) -> KubeMessage {
+    KubeMessage::Action(Err(KubeError::new(action_name.as_str(), err)))
+}
+
 fn success(action_name: String, results: String) -> KubeMessage {
+    result(action_name, results, ActionOutcome::Done)
+}
+
+fn progress(action_name: String, results: String) -> KubeMessage {
+    result(action_name, results, ActionOutcome::InProgress)
+}
+
+fn needs_confirmation(action_name: String, namespace: &str, pods: Vec<String>) -> KubeMessage {
+    KubeMessage::Action(Ok(ActionResult {
+        name: action_name,
+        results: pods.join("\n"),
+        outcome: ActionOutcome::NeedsConfirmation,
+        secrets: vec![],
+        output: None,
+        pods: Some((namespace.to_owned(), pods)),
+    }))
+}
+
+/// For actions that went wrong without kubernetes returning an error
+fn failed(action_name: String, results: String) -> KubeMessage {
+    result(action_name, results, ActionOutcome::Failed)
+}
+
+fn result(action_name: String, results: String, outcome: ActionOutcome) -> KubeMessage {
     KubeMessage::Action(Ok(ActionResult {
         name: action_name,
         results,
+        outcome,
+        secrets: vec![],
+        output: None,
+        pods: None,
     }))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use k8s_openapi::api::core::v1::{PodCondition, PodStatus};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+
+    #[cfg(test)]
+    mod configmap_lines {
+        use super::*;
+        use k8s_openapi::ByteString;
+
+        #[test]
+        pub fn lists_data_then_binary_sizes() {
+            let cm = ConfigMap {
+                data: Some(
+                    [
+                        ("LOG_LEVEL".to_owned(), "debug".to_owned()),
+                        ("APP_ENV".to_owned(), "dev".to_owned()),
+                    ]
+                    .into_iter()
+                    .collect(),
+                ),
+                binary_data: Some(
+                    [("cert.der".to_owned(), ByteString(vec![0, 1, 2, 3]))]
+                        .into_iter()
+                        .collect(),
+                ),
+                ..Default::default()
+            };
+            assert_eq!(
+                configmap_lines(&cm),
+                vec![
+                    "APP_ENV: dev".to_owned(),
+                    "LOG_LEVEL: debug".to_owned(),
+                    "cert.der: <binary, 4 bytes>".to_owned(),
+                ]
+            );
+        }
+
+        #[test]
+        pub fn empty_configmap_has_no_lines() {
+            assert!(configmap_lines(&ConfigMap::default()).is_empty());
+        }
+    }
+
+    #[cfg(test)]
+    mod pod_selection {
+        use super::*;
+
+        #[test]
+        pub fn plain_names_are_names() {
+            assert_eq!(
+                pod_selection("web-5d9c7b-x2x7k"),
+                PodSelection::Name("web-5d9c7b-x2x7k".to_owned())
+            );
+        }
+
+        #[test]
+        pub fn selectors_are_labels() {
+            assert_eq!(
+                pod_selection("app=web,tier!=db"),
+                PodSelection::Labels("app=web,tier!=db".to_owned())
+            );
+            assert_eq!(
+                pod_selection("env in (dev)"),
+                PodSelection::Labels("env in (dev)".to_owned())
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod exec_target {
+        use super::*;
+
+        #[test]
+        pub fn names_are_deployments() {
+            assert_eq!(
+                exec_target("my-app"),
+                ExecTarget::Deployment("my-app".to_owned())
+            );
+        }
+
+        #[test]
+        pub fn prefixed_names_are_pods() {
+            assert_eq!(
+                exec_target("pod/my-app-5d9c7b-x2x7k"),
+                ExecTarget::Pod("my-app-5d9c7b-x2x7k".to_owned())
+            );
+        }
+
+        #[test]
+        pub fn selectors_are_labels() {
+            assert_eq!(
+                exec_target("app=my-app"),
+                ExecTarget::Labels("app=my-app".to_owned())
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod ready_replacements {
+        use super::*;
+
+        #[test]
+        pub fn counts_ready_pods_with_same_owner() {
+            let owners = vec!["rs-1".to_owned()];
+            let existing = vec!["old".to_owned()];
+            let pods = vec![
+                pod("old", "rs-1", true),
+                pod("new", "rs-1", true),
+                pod("starting", "rs-1", false),
+                pod("other", "rs-2", true),
+            ];
+            assert_eq!(ready_replacements(&pods, &owners, &existing), 1);
+        }
+
+        #[test]
+        pub fn ignores_ready_siblings() {
+            let owners = vec!["rs-1".to_owned()];
+            let existing = vec!["old".to_owned(), "sibling".to_owned()];
+            let pods = vec![pod("sibling", "rs-1", true), pod("new", "rs-1", false)];
+            assert_eq!(ready_replacements(&pods, &owners, &existing), 0);
+        }
+    }
+
+    fn pod(name: &str, owner: &str, ready: bool) -> Pod {
+        let mut pod = Pod::default();
+        pod.metadata.name = Some(name.to_owned());
+        pod.metadata.uid = Some(name.to_owned());
+        pod.metadata.owner_references = Some(vec![OwnerReference {
+            uid: owner.to_owned(),
+            ..Default::default()
+        }]);
+        pod.status = Some(PodStatus {
+            conditions: Some(vec![PodCondition {
+                type_: "Ready".to_owned(),
+                status: if ready { "True" } else { "False" }.to_owned(),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        });
+        pod
+    }
+}
@@ -0,0 +1,327 @@
+use super::channel::MessageSender;
+use super::client::ClientManager;
+use super::{Error, KubeError};
+use crate::KubeMessage;
+use kube::api::{Api, ApiResource, DynamicObject, ListParams, Patch, PatchParams};
+use kube::discovery::{verbs, Discovery, Scope};
+use kube::Client;
+use serde_json::Value;
+use std::cmp::Reverse;
+
+/// Who changes made from the app are recorded as coming from
+pub const FIELD_MANAGER: &str = "kube-switchboard";
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SortBy {
+    Name,
+    /// Youngest first
+    Age,
+    Status,
+}
+
+/// Objects of one kind listed in a namespace, tagged with both so late answers for
+/// an earlier pick can be told apart
+pub struct ObjectList {
+    pub namespace: String,
+    pub kind: ApiResource,
+    pub objects: Result<Vec<DynamicObject>, KubeError>,
+}
+
+/// Finds every namespaced kind the cluster can list, CRDs included, sorted by kind.
+/// The answer is tagged with `id`.
+pub fn discover_kinds(clients: ClientManager, id: u64, tx: MessageSender) {
+    tokio::spawn(async move {
+        let kinds = match clients.client().await {
+            Ok(client) => namespaced_kinds(client).await.map_err(Error::from),
+            Err(err) => Err(err),
+        };
+        let _ = tx.send(KubeMessage::Kinds(id, kinds));
+    });
+}
+
+async fn namespaced_kinds(client: Client) -> Result<Vec<ApiResource>, kube::Error> {
+    let discovery = Discovery::new(client).run().await?;
+    let mut kinds: Vec<ApiResource> = discovery
+        .groups()
+        .flat_map(|group| group.recommended_resources())
+        .filter(|(_, caps)| caps.scope == Scope::Namespaced && caps.supports_operation(verbs::LIST))
+        .map(|(kind, _)| kind)
+        .collect();
+    kinds.sort_by(|a, b| (&a.kind, &a.group).cmp(&(&b.kind, &b.group)));
+    Ok(kinds)
+}
+
+pub fn list_objects(
+    clients: ClientManager,
+    namespace: String,
+    kind: ApiResource,
+    tx: MessageSender,
+) {
+    tokio::spawn(async move {
+        let objects = match clients.client().await {
+            Ok(client) => {
+                let api: Api<DynamicObject> = Api::namespaced_with(client, &namespace, &kind);
+                api.list(&ListParams::default())
+                    .await
+                    .map(|list| list.items)
+                    .map_err(Error::from)
+            }
+            Err(err) => Err(err),
+        };
+        let objects = objects.map_err(|err| KubeError::new(&kind.kind, err));
+        let list = ObjectList {
+            namespace,
+            kind,
+            objects,
+        };
+        let _ = tx.send(KubeMessage::Objects(list));
+    });
+}
+
+/// A one-word status for any kind of object, from the fields most kinds share: a
+/// `phase`, the `Ready` or `Available` condition, or ready replicas out of wanted
+pub fn object_status(object: &DynamicObject) -> String {
+    let status = match object.data.get("status") {
+        Some(status) => status,
+        None => return "".to_owned(),
+    };
+    if let Some(phase) = status.get("phase").and_then(Value::as_str) {
+        return phase.to_owned();
+    }
+    let conditions = status
+        .get("conditions")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    for wanted in ["Ready", "Available"] {
+        let condition = conditions
+            .iter()
+            .find(|c| c.get("type").and_then(Value::as_str) == Some(wanted));
+        if let Some(condition) = condition {
+            let is = condition.get("status").and_then(Value::as_str) == Some("True");
+            return if is {
+                wanted.to_owned()
+            } else {
+                format!("Not {}", wanted.to_lowercase())
+            };
+        }
+    }
+    let replicas = object
+        .data
+        .get("spec")
+        .and_then(|s| s.get("replicas"))
+        .and_then(Value::as_i64);
+    if let Some(replicas) = replicas {
+        let ready = status
+            .get("readyReplicas")
+            .and_then(Value::as_i64)
+            .unwrap_or(0);
+        return format!("{}/{} ready", ready, replicas);
+    }
+    "".to_owned()
+}
+
+pub fn sort_objects(objects: &mut [DynamicObject], by: SortBy, descending: bool) {
+    match by {
+        SortBy::Name => objects.sort_by_key(|o| o.metadata.name.clone()),
+        SortBy::Age => objects.sort_by_key(|o| Reverse(o.metadata.creation_timestamp.clone())),
+        SortBy::Status => objects.sort_by_key(|o| (object_status(o), o.metadata.name.clone())),
+    }
+    if descending {
+        objects.reverse();
+    }
+}
+
+/// Fetches one object for the detail view with the given id
+pub fn get_object(
+    clients: ClientManager,
+    namespace: String,
+    kind: ApiResource,
+    name: String,
+    id: u64,
+    tx: MessageSender,
+) {
+    tokio::spawn(async move {
+        let object = match clients.client().await {
+            Ok(client) => {
+                let api: Api<DynamicObject> = Api::namespaced_with(client, &namespace, &kind);
+                api.get(&name).await.map(Box::new).map_err(Error::from)
+            }
+            Err(err) => Err(err),
+        };
+        let object = object.map_err(|err| KubeError::new(&name, err));
+        let _ = tx.send(KubeMessage::Object(id, object));
+    });
+}
+
+/// What server-side applying an edit came back with
+#[derive(Clone)]
+pub enum Applied {
+    /// A dry run, with the object as it is now and as it would become
+    Checked {
+        live: Box<DynamicObject>,
+        applied: Box<DynamicObject>,
+    },
+    Done(Box<DynamicObject>),
+    /// Other managers own fields the edit changes, so it only applies when forced
+    Conflict(String),
+}
+
+/// Server-side applies `object` to `name`, only taking over fields from their other
+/// managers when forced. A dry run also fetches the object as it is now, to compare
+/// with what it would become. The result goes to the detail view with the given id.
+#[allow(clippy::too_many_arguments)]
+pub fn apply_object(
+    clients: ClientManager,
+    namespace: String,
+    kind: ApiResource,
+    name: String,
+    object: Value,
+    force: bool,
+    dry_run: bool,
+    id: u64,
+    tx: MessageSender,
+) {
+    tokio::spawn(async move {
+        let applied = match clients.client().await {
+            Ok(client) => {
+                let api: Api<DynamicObject> = Api::namespaced_with(client, &namespace, &kind);
+                apply(&api, &name, &object, force, dry_run).await
+            }
+            Err(err) => Err(err),
+        };
+        let applied = applied.map_err(|err| KubeError::new(&name, err));
+        let _ = tx.send(KubeMessage::Applied(id, applied));
+    });
+}
+
+async fn apply(
+    api: &Api<DynamicObject>,
+    name: &str,
+    object: &Value,
+    force: bool,
+    dry_run: bool,
+) -> Result<Applied, Error> {
+    let mut params = PatchParams::apply(FIELD_MANAGER);
+    if force {
+        params = params.force();
+    }
+    let patch = Patch::Apply(object);
+    if !dry_run {
+        return match api.patch(name, &params, &patch).await {
+            Ok(applied) => Ok(Applied::Done(Box::new(applied))),
+            Err(err) => conflict(err),
+        };
+    }
+    let params = params.dry_run();
+    let (applied, live) = futures::join!(api.patch(name, &params, &patch), api.get(name));
+    match applied {
+        Ok(applied) => Ok(Applied::Checked {
+            live: Box::new(live?),
+            applied: Box::new(applied),
+        }),
+        Err(err) => conflict(err),
+    }
+}
+
+// A conflict is something to decide on rather than a failure
+fn conflict(err: kube::Error) -> Result<Applied, Error> {
+    match err {
+        kube::Error::Api(resp) if resp.code == 409 => Ok(Applied::Conflict(resp.message)),
+        err => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn object(data: Value) -> DynamicObject {
+        serde_json::from_value(data).unwrap()
+    }
+
+    #[cfg(test)]
+    mod object_status {
+        use super::*;
+
+        #[test]
+        pub fn prefers_phase() {
+            let pod = object(json!({"status": {"phase": "Running"}}));
+            assert_eq!(object_status(&pod), "Running");
+        }
+
+        #[test]
+        pub fn reads_ready_condition() {
+            let cert = object(json!({"status": {"conditions": [
+                {"type": "Issuing", "status": "True"},
+                {"type": "Ready", "status": "False"}
+            ]}}));
+            assert_eq!(object_status(&cert), "Not ready");
+        }
+
+        #[test]
+        pub fn counts_ready_replicas() {
+            let set = object(json!({
+                "spec": {"replicas": 3},
+                "status": {"readyReplicas": 2}
+            }));
+            assert_eq!(object_status(&set), "2/3 ready");
+        }
+
+        #[test]
+        pub fn empty_without_status() {
+            let cm = object(json!({"data": {"key": "value"}}));
+            assert_eq!(object_status(&cm), "");
+        }
+    }
+
+    #[cfg(test)]
+    mod sort_objects {
+        use super::*;
+
+        fn names(objects: &[DynamicObject]) -> Vec<String> {
+            objects
+                .iter()
+                .map(|o| o.metadata.name.clone().unwrap_or_default())
+                .collect()
+        }
+
+        fn objects() -> Vec<DynamicObject> {
+            vec![
+                object(json!({"metadata": {
+                    "name": "b", "creationTimestamp": "2026-01-01T00:00:00Z"
+                }, "status": {"phase": "Pending"}})),
+                object(json!({"metadata": {
+                    "name": "a", "creationTimestamp": "2026-03-01T00:00:00Z"
+                }, "status": {"phase": "Running"}})),
+                object(json!({"metadata": {
+                    "name": "c", "creationTimestamp": "2026-02-01T00:00:00Z"
+                }, "status": {"phase": "Failed"}})),
+            ]
+        }
+
+        #[test]
+        pub fn sorts_by_name_either_way() {
+            let mut objects = objects();
+            sort_objects(&mut objects, SortBy::Name, false);
+            assert_eq!(names(&objects), vec!["a", "b", "c"]);
+            sort_objects(&mut objects, SortBy::Name, true);
+            assert_eq!(names(&objects), vec!["c", "b", "a"]);
+        }
+
+        #[test]
+        pub fn sorts_youngest_first_by_age() {
+            let mut objects = objects();
+            sort_objects(&mut objects, SortBy::Age, false);
+            assert_eq!(names(&objects), vec!["a", "c", "b"]);
+        }
+
+        #[test]
+        pub fn sorts_by_status() {
+            let mut objects = objects();
+            sort_objects(&mut objects, SortBy::Status, false);
+            assert_eq!(names(&objects), vec!["c", "b", "a"]);
+        }
+    }
+}
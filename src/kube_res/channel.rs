@@ -0,0 +1,26 @@
+use super::KubeMessage;
+use eframe::egui;
+use std::sync::mpsc::{self, Receiver, SendError, Sender};
+
+/// Sends messages from background tasks to the UI and wakes it up so they are shown
+/// straight away, rather than on the next mouse move
+#[derive(Clone)]
+pub struct MessageSender {
+    tx: Sender<KubeMessage>,
+    ctx: egui::Context,
+}
+
+impl MessageSender {
+    // Same signature as `Sender::send`, so callers can treat it like one
+    #[allow(clippy::result_large_err)]
+    pub fn send(&self, msg: KubeMessage) -> Result<(), SendError<KubeMessage>> {
+        self.tx.send(msg)?;
+        self.ctx.request_repaint();
+        Ok(())
+    }
+}
+
+pub fn channel(ctx: egui::Context) -> (MessageSender, Receiver<KubeMessage>) {
+    let (tx, rx) = mpsc::channel();
+    (MessageSender { tx, ctx }, rx)
+}
@@ -0,0 +1,129 @@
+use super::channel::MessageSender;
+use super::Error;
+use crate::KubeMessage;
+use kube::{config::KubeConfigOptions, Client, Config};
+use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::Mutex;
+
+/// Hands out clones of one shared kube `Client`, building it on first use and
+/// rebuilding it only when the selected context or the kubeconfig on disk changes.
+#[derive(Clone)]
+pub struct ClientManager {
+    state: Arc<Mutex<ClientState>>,
+    /// Where (re)builds are reported, only set for the client the topbar controls
+    tx: Option<MessageSender>,
+}
+
+#[derive(Default)]
+struct ClientState {
+    client: Option<Client>,
+    /// Context picked in the UI, `None` uses the kubeconfig's current context
+    context: Option<String>,
+    kubeconfig_stamp: Vec<Option<SystemTime>>,
+    failure_reported: bool,
+}
+
+impl ClientManager {
+    pub fn new(tx: MessageSender) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ClientState::default())),
+            tx: Some(tx),
+        }
+    }
+
+    /// A manager pinned to one context that does not report to the UI, for talking
+    /// to clusters other than the one selected in the topbar
+    pub fn for_context(context: String) -> Self {
+        let state = ClientState {
+            context: Some(context),
+            ..Default::default()
+        };
+        Self {
+            state: Arc::new(Mutex::new(state)),
+            tx: None,
+        }
+    }
+
+    /// Switches to another kubeconfig context. The client is rebuilt on next use.
+    pub async fn set_context(&self, context: Option<String>) {
+        let mut state = self.state.lock().await;
+        if state.context != context {
+            state.context = context;
+            state.client = None;
+            state.failure_reported = false;
+        }
+    }
+
+    /// A client for the selected context. A failure to build one is reported to the
+    /// UI the first time it happens; callers still get the error to handle themselves.
+    pub async fn client(&self) -> Result<Client, Error> {
+        let mut state = self.state.lock().await;
+        let stamp = kubeconfig_stamp();
+        if let Some(client) = &state.client {
+            if state.kubeconfig_stamp == stamp {
+                return Ok(client.clone());
+            }
+        }
+
+        state.client = None;
+        state.kubeconfig_stamp = stamp;
+        match build_client(state.context.clone()).await {
+            Ok((client, cluster)) => {
+                state.client = Some(client.clone());
+                state.failure_reported = false;
+                self.report(Ok(cluster));
+                Ok(client)
+            }
+            Err(err) => {
+                if !state.failure_reported {
+                    state.failure_reported = true;
+                    self.report(Err(err.to_string()));
+                }
+                Err(err)
+            }
+        }
+    }
+
+    fn report(&self, result: Result<String, String>) {
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(KubeMessage::Client(result));
+        }
+    }
+}
+
+/// Builds a client, returning it with the URL of the cluster it talks to
+async fn build_client(context: Option<String>) -> Result<(Client, String), Error> {
+    let config = match context {
+        Some(context) => {
+            let options = KubeConfigOptions {
+                context: Some(context),
+                ..Default::default()
+            };
+            Config::from_kubeconfig(&options).await?
+        }
+        None => Config::infer().await.map_err(kube::Error::InferConfig)?,
+    };
+    let cluster = config.cluster_url.to_string();
+    Ok((Client::try_from(config)?, cluster))
+}
+
+/// Modification times of every kubeconfig file in use, so that a `kubectl config
+/// use-context` or a credentials refresh can be noticed
+fn kubeconfig_stamp() -> Vec<Option<SystemTime>> {
+    kubeconfig_paths()
+        .iter()
+        .map(|path| path.metadata().and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+fn kubeconfig_paths() -> Vec<PathBuf> {
+    match env::var_os("KUBECONFIG") {
+        Some(paths) => env::split_paths(&paths).collect(),
+        None => env::home_dir()
+            .map(|home| vec![home.join(".kube").join("config")])
+            .unwrap_or_default(),
+    }
+}
@@ -0,0 +1,90 @@
+use super::channel::MessageSender;
+use super::client::ClientManager;
+use super::Error;
+use super::{deployments, pods, services};
+use crate::config::Thresholds;
+use crate::{KubeMessage, KubeResource, KubeStatus};
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::{Pod, Service};
+use k8s_openapi::NamespaceResourceScope;
+use kube::{
+    api::{Api, ListParams},
+    Client, Resource,
+};
+use serde::de::DeserializeOwned;
+use std::borrow::Borrow;
+use std::fmt::Debug;
+
+/// Runs the pod, service and deployment checks once against `namespace` in the
+/// cluster behind `clients`, reporting each result tagged with `id` and `context`
+#[allow(clippy::too_many_arguments)]
+pub fn check_cluster(
+    context: String,
+    clients: ClientManager,
+    namespace: String,
+    expected_services: Vec<String>,
+    expected_deploys: Vec<String>,
+    thresholds: Thresholds,
+    id: u64,
+    tx: MessageSender,
+) {
+    tokio::spawn(async move {
+        let resources = match clients.client().await {
+            Ok(client) => {
+                let (pods_list, services_list, deploys_list) = futures::join!(
+                    list::<Pod>(client.clone(), &namespace),
+                    list::<Service>(client.clone(), &namespace),
+                    list::<Deployment>(client, &namespace),
+                );
+                vec![
+                    services::resource(
+                        services_list
+                            .map(|list| services::services_status(expected_services, &list))
+                            .unwrap_or_else(failed),
+                    ),
+                    deployments::resource(
+                        deploys_list
+                            .map(|list| deployments::deployments_status(expected_deploys, &list))
+                            .unwrap_or_else(failed),
+                    ),
+                    pods::resource(
+                        pods_list
+                            .map(|list| pods::pods_status(&list, &thresholds))
+                            .unwrap_or_else(failed),
+                    ),
+                ]
+            }
+            Err(err) => vec![
+                services::resource(failed(&err)),
+                deployments::resource(failed(&err)),
+                pods::resource(failed(&err)),
+            ],
+        };
+        for resource in resources {
+            if let Err(e) = tx.send(cluster_message(id, &context, resource)) {
+                println!("Failed sending message about cluster {}: {}", context, e);
+            }
+        }
+    });
+}
+
+async fn list<K>(client: Client, namespace: &str) -> Result<Vec<K>, Error>
+where
+    K: Resource<Scope = NamespaceResourceScope> + Clone + DeserializeOwned + Debug,
+    K::DynamicType: Default,
+{
+    let api: Api<K> = Api::namespaced(client, namespace);
+    api.list(&ListParams::default())
+        .await
+        .map(|list| list.items)
+        .map_err(Error::from)
+}
+
+// A cluster we cannot talk to still needs a status in every cell of the grid
+fn failed<E: Borrow<Error>>(err: E) -> KubeStatus {
+    KubeStatus::Error(err.borrow().describe())
+}
+
+fn cluster_message(id: u64, context: &str, resource: KubeResource) -> KubeMessage {
+    KubeMessage::Cluster(id, context.to_owned(), resource)
+}
@@ -0,0 +1,105 @@
+use super::channel::MessageSender;
+use super::client::ClientManager;
+use super::namespaces::get_namespaces;
+use crate::KubeMessage;
+use kube::config::Kubeconfig;
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct KubeContext {
+    pub name: String,
+    pub cluster: String,
+    pub user: String,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct KubeContexts {
+    pub current: Option<String>,
+    pub contexts: Vec<KubeContext>,
+}
+
+pub fn get_contexts(tx: MessageSender) {
+    tokio::spawn(async move {
+        let contexts = Kubeconfig::read()
+            .map(|config| kube_contexts(&config))
+            .map_err(|err| err.to_string());
+        let _ = tx.send(KubeMessage::Contexts(contexts));
+    });
+}
+
+/// Points the shared client at `context` and reloads the namespaces it can see
+pub fn switch_context(clients: ClientManager, context: String, tx: MessageSender) {
+    tokio::spawn(async move {
+        clients.set_context(Some(context)).await;
+        get_namespaces(clients, tx);
+    });
+}
+
+fn kube_contexts(config: &Kubeconfig) -> KubeContexts {
+    KubeContexts {
+        current: config.current_context.clone(),
+        contexts: config
+            .contexts
+            .iter()
+            .map(|named| {
+                let (cluster, user) = match &named.context {
+                    Some(ctx) => (ctx.cluster.clone(), ctx.user.clone()),
+                    None => ("".to_owned(), "".to_owned()),
+                };
+                KubeContext {
+                    name: named.name.clone(),
+                    cluster,
+                    user,
+                }
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(test)]
+    mod kube_contexts {
+        use super::*;
+
+        #[test]
+        pub fn lists_contexts_with_cluster_and_user() {
+            let config = Kubeconfig::from_yaml(
+                r#"
+apiVersion: v1
+kind: Config
+current-context: staging
+contexts:
+  - name: staging
+    context:
+      cluster: staging-cluster
+      user: alice
+  - name: prod
+    context:
+      cluster: prod-cluster
+      user: bob
+"#,
+            )
+            .expect("valid kubeconfig");
+
+            let contexts = kube_contexts(&config);
+            assert_eq!(contexts.current, Some("staging".to_owned()));
+            assert_eq!(
+                contexts.contexts,
+                vec![
+                    KubeContext {
+                        name: "staging".to_owned(),
+                        cluster: "staging-cluster".to_owned(),
+                        user: "alice".to_owned(),
+                    },
+                    KubeContext {
+                        name: "prod".to_owned(),
+                        cluster: "prod-cluster".to_owned(),
+                        user: "bob".to_owned(),
+                    },
+                ]
+            );
+        }
+    }
+}
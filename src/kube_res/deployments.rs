@@ -0,0 +1,434 @@
+use super::channel::MessageSender;
+use super::client::ClientManager;
+use super::watch::watch_namespaced;
+use super::KubeError;
+use crate::{KubeMessage, KubeResource, KubeStatus};
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use kube::api::{Api, ListParams};
+use kube::{Client, Error};
+use tokio::task::JoinHandle;
+
+/// Watches the deployments in `namespace`, reporting missing and unhealthy ones
+pub fn watch_deployments(
+    clients: ClientManager,
+    namespace: String,
+    generation: u64,
+    expected_deploys: Vec<String>,
+    tx: MessageSender,
+) -> JoinHandle<()> {
+    watch_namespaced(
+        clients,
+        namespace,
+        generation,
+        tx,
+        move |deploys: Vec<Deployment>| {
+            vec![success(deployments_status(
+                expected_deploys.clone(),
+                &deploys,
+            ))]
+        },
+        error,
+    )
+}
+
+pub fn deployments_status(expected: Vec<String>, deploys: &[Deployment]) -> KubeStatus {
+    let names: Vec<Option<String>> = deploys.iter().map(|d| d.metadata.name.clone()).collect();
+    let missing = missing_deployments(expected.clone(), names);
+
+    let mut bad: Vec<String> = vec![];
+    let mut suspicious: Vec<String> = vec![];
+    if !missing.is_empty() {
+        bad.push(format!("Deployments not in k8s: {}", missing.join(", ")));
+    }
+    // Only the expected deployments count, others in the namespace are not ours to judge
+    let checked = deploys.iter().filter(|d| {
+        d.metadata
+            .name
+            .as_ref()
+            .is_some_and(|name| expected.contains(name))
+    });
+    for deploy in checked {
+        match deployment_status(deploy) {
+            KubeStatus::Bad(msg) => bad.push(msg),
+            KubeStatus::Suspicious(msg) => suspicious.push(msg),
+            _ => {}
+        }
+    }
+
+    if !bad.is_empty() {
+        bad.append(&mut suspicious);
+        KubeStatus::Bad(bad.join("; "))
+    } else if !suspicious.is_empty() {
+        KubeStatus::Suspicious(suspicious.join("; "))
+    } else {
+        KubeStatus::Good
+    }
+}
+
+fn deployment_status(deploy: &Deployment) -> KubeStatus {
+    let name = deploy.metadata.name.clone().unwrap_or("unknown".to_owned());
+    // Kubernetes defaults `spec.replicas` to 1 when it is not set
+    let desired = deploy.spec.as_ref().and_then(|s| s.replicas).unwrap_or(1);
+    let (ready, updated, available) = match &deploy.status {
+        Some(s) => (
+            s.ready_replicas.unwrap_or(0),
+            s.updated_replicas.unwrap_or(0),
+            s.available_replicas.unwrap_or(0),
+        ),
+        None => (0, 0, 0),
+    };
+
+    if desired == 0 {
+        KubeStatus::Suspicious(format!("{} is scaled to 0 replicas", name))
+    } else if available == 0 {
+        KubeStatus::Bad(format!(
+            "{} has no available replicas (0/{})",
+            name, desired
+        ))
+    } else if ready < desired || updated < desired || available < desired {
+        KubeStatus::Suspicious(format!(
+            "{} has {}/{} ready, {}/{} updated, {}/{} available",
+            name, ready, desired, updated, desired, available, desired
+        ))
+    } else {
+        KubeStatus::Good
+    }
+}
+
+fn missing_deployments(expected: Vec<String>, actual: Vec<Option<String>>) -> Vec<String> {
+    expected
+        .into_iter()
+        .filter(|deploy| !actual.contains(&Some(deploy.clone())))
+        .collect()
+}
+
+#[derive(PartialEq, Debug)]
+pub enum Rollout {
+    Progressing(String),
+    Complete(String),
+    Failed(String),
+}
+
+/// Where a deployment's rollout is at, following the checks `kubectl rollout status`
+/// makes
+pub fn rollout_status(deploy: &Deployment) -> Rollout {
+    let name = deploy.metadata.name.clone().unwrap_or("unknown".to_owned());
+    let status = deploy.status.clone().unwrap_or_default();
+    if deploy.metadata.generation > status.observed_generation {
+        return Rollout::Progressing(format!("Waiting for {} to be picked up", name));
+    }
+    let deadline_exceeded = status.conditions.iter().flatten().any(|c| {
+        c.type_ == "Progressing" && c.reason.as_deref() == Some("ProgressDeadlineExceeded")
+    });
+    if deadline_exceeded {
+        return Rollout::Failed(format!("{} exceeded its progress deadline", name));
+    }
+    let wanted = deploy.spec.as_ref().and_then(|s| s.replicas).unwrap_or(1);
+    let updated = status.updated_replicas.unwrap_or(0);
+    let total = status.replicas.unwrap_or(0);
+    let available = status.available_replicas.unwrap_or(0);
+    if updated < wanted {
+        Rollout::Progressing(format!(
+            "{} of {} new replicas have been updated",
+            updated, wanted
+        ))
+    } else if total > updated {
+        Rollout::Progressing(format!(
+            "{} old replicas are pending termination",
+            total - updated
+        ))
+    } else if available < updated {
+        Rollout::Progressing(format!(
+            "{} of {} updated replicas are available",
+            available, updated
+        ))
+    } else {
+        Rollout::Complete(format!("{} successfully rolled out", name))
+    }
+}
+
+/// Names of the pods matched by the label selector of deployment `name`
+pub async fn deployment_pods(
+    client: Client,
+    namespace: &str,
+    name: &str,
+) -> Result<Vec<String>, Error> {
+    let selector = match deployment_selector(client.clone(), namespace, name).await? {
+        Some(selector) => selector,
+        None => return Ok(vec![]),
+    };
+    let pods: Api<Pod> = Api::namespaced(client, namespace);
+    let list = pods.list(&ListParams::default().labels(&selector)).await?;
+    Ok(list
+        .items
+        .into_iter()
+        .filter_map(|p| p.metadata.name)
+        .collect())
+}
+
+/// The label selector of a deployment's pods, in the form `ListParams::labels` takes.
+/// None when it is empty, as that matches every pod in the namespace, which is never
+/// what we want.
+pub async fn deployment_selector(
+    client: Client,
+    namespace: &str,
+    name: &str,
+) -> Result<Option<String>, Error> {
+    let deploys: Api<Deployment> = Api::namespaced(client, namespace);
+    let deploy = deploys.get(name).await?;
+    Ok(deploy
+        .spec
+        .map(|spec| selector_string(&spec.selector))
+        .filter(|selector| !selector.is_empty()))
+}
+
+/// Turns a label selector into the `key=value,key in (a,b)` form the API takes
+fn selector_string(selector: &LabelSelector) -> String {
+    let labels = selector
+        .match_labels
+        .iter()
+        .flatten()
+        .map(|(key, value)| format!("{}={}", key, value));
+    let expressions = selector.match_expressions.iter().flatten().map(|expr| {
+        let values = expr.values.clone().unwrap_or_default().join(",");
+        match expr.operator.as_str() {
+            "In" => format!("{} in ({})", expr.key, values),
+            "NotIn" => format!("{} notin ({})", expr.key, values),
+            "DoesNotExist" => format!("!{}", expr.key),
+            _ => expr.key.clone(),
+        }
+    });
+    labels.chain(expressions).collect::<Vec<String>>().join(",")
+}
+
+pub fn resource(status: KubeStatus) -> KubeResource {
+    KubeResource {
+        name: "deployment".to_owned(),
+        display: "Deploys".to_owned(),
+        status,
+    }
+}
+
+fn success(status: KubeStatus) -> KubeMessage {
+    KubeMessage::Resource(Ok(resource(status)))
+}
+
+fn error(err: super::Error) -> KubeMessage {
+    KubeMessage::Resource(Err(KubeError::new("deployment", err)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use k8s_openapi::api::apps::v1::{DeploymentSpec, DeploymentStatus};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+
+    #[cfg(test)]
+    mod deployment_status {
+        use super::*;
+
+        #[test]
+        pub fn is_good_when_fully_rolled_out() {
+            let d = deploy("app", Some(3), Some((3, 3, 3)));
+            assert_eq!(deployment_status(&d), KubeStatus::Good);
+        }
+
+        #[test]
+        pub fn is_bad_when_nothing_available() {
+            let d = deploy("app", Some(2), Some((0, 2, 0)));
+            assert_eq!(
+                deployment_status(&d),
+                KubeStatus::Bad("app has no available replicas (0/2)".to_owned())
+            );
+        }
+
+        #[test]
+        pub fn is_bad_when_no_status() {
+            let d = deploy("app", None, None);
+            assert_eq!(
+                deployment_status(&d),
+                KubeStatus::Bad("app has no available replicas (0/1)".to_owned())
+            );
+        }
+
+        #[test]
+        pub fn is_sus_when_partially_ready() {
+            let d = deploy("app", Some(3), Some((2, 1, 2)));
+            assert_eq!(
+                deployment_status(&d),
+                KubeStatus::Suspicious("app has 2/3 ready, 1/3 updated, 2/3 available".to_owned())
+            );
+        }
+
+        #[test]
+        pub fn is_sus_when_scaled_to_zero() {
+            let d = deploy("app", Some(0), Some((0, 0, 0)));
+            assert_eq!(
+                deployment_status(&d),
+                KubeStatus::Suspicious("app is scaled to 0 replicas".to_owned())
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod deployments_status {
+        use super::*;
+
+        #[test]
+        pub fn is_bad_when_expected_deployment_missing() {
+            let deploys = vec![deploy("app", Some(1), Some((1, 1, 1)))];
+            let expected = vec!["app".to_owned(), "worker".to_owned()];
+            assert_eq!(
+                deployments_status(expected, &deploys),
+                KubeStatus::Bad("Deployments not in k8s: worker".to_owned())
+            );
+        }
+
+        #[test]
+        pub fn bad_includes_suspicious_messages() {
+            let deploys = vec![
+                deploy("app", Some(2), Some((1, 2, 1))),
+                deploy("worker", Some(1), Some((0, 0, 0))),
+            ];
+            let expected = vec!["app".to_owned(), "worker".to_owned()];
+            assert_eq!(
+                deployments_status(expected, &deploys),
+                KubeStatus::Bad(
+                    "worker has no available replicas (0/1); app has 1/2 ready, 2/2 updated, 1/2 available"
+                        .to_owned()
+                )
+            );
+        }
+
+        #[test]
+        pub fn ignores_unexpected_deployments() {
+            let deploys = vec![
+                deploy("app", Some(2), Some((2, 2, 2))),
+                deploy("someone-elses", Some(1), Some((0, 0, 0))),
+            ];
+            let expected = vec!["app".to_owned()];
+            assert_eq!(deployments_status(expected, &deploys), KubeStatus::Good);
+        }
+
+        #[test]
+        pub fn is_good_when_all_present_and_ready() {
+            let deploys = vec![
+                deploy("app", Some(2), Some((2, 2, 2))),
+                deploy("worker", Some(1), Some((1, 1, 1))),
+            ];
+            let expected = vec!["app".to_owned(), "worker".to_owned()];
+            assert_eq!(deployments_status(expected, &deploys), KubeStatus::Good);
+        }
+    }
+
+    #[cfg(test)]
+    mod rollout_status {
+        use super::*;
+        use k8s_openapi::api::apps::v1::DeploymentCondition;
+
+        fn rolled(total: i32, updated: i32, available: i32) -> Deployment {
+            let mut d = deploy("app", Some(3), Some((available, updated, available)));
+            d.status.as_mut().unwrap().replicas = Some(total);
+            d
+        }
+
+        #[test]
+        pub fn waits_for_new_generation_to_be_observed() {
+            let mut d = rolled(3, 3, 3);
+            d.metadata.generation = Some(2);
+            d.status.as_mut().unwrap().observed_generation = Some(1);
+            assert!(matches!(rollout_status(&d), Rollout::Progressing(_)));
+        }
+
+        #[test]
+        pub fn reports_each_stage() {
+            assert_eq!(
+                rollout_status(&rolled(4, 1, 1)),
+                Rollout::Progressing("1 of 3 new replicas have been updated".to_owned())
+            );
+            assert_eq!(
+                rollout_status(&rolled(4, 3, 3)),
+                Rollout::Progressing("1 old replicas are pending termination".to_owned())
+            );
+            assert_eq!(
+                rollout_status(&rolled(3, 3, 2)),
+                Rollout::Progressing("2 of 3 updated replicas are available".to_owned())
+            );
+            assert_eq!(
+                rollout_status(&rolled(3, 3, 3)),
+                Rollout::Complete("app successfully rolled out".to_owned())
+            );
+        }
+
+        #[test]
+        pub fn fails_past_progress_deadline() {
+            let mut d = rolled(4, 1, 1);
+            d.status.as_mut().unwrap().conditions = Some(vec![DeploymentCondition {
+                type_: "Progressing".to_owned(),
+                status: "False".to_owned(),
+                reason: Some("ProgressDeadlineExceeded".to_owned()),
+                ..Default::default()
+            }]);
+            assert_eq!(
+                rollout_status(&d),
+                Rollout::Failed("app exceeded its progress deadline".to_owned())
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod selector_string {
+        use super::*;
+        use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelectorRequirement;
+
+        #[test]
+        pub fn joins_labels_and_expressions() {
+            let selector = LabelSelector {
+                match_labels: Some([("app".to_owned(), "web".to_owned())].into_iter().collect()),
+                match_expressions: Some(vec![
+                    LabelSelectorRequirement {
+                        key: "tier".to_owned(),
+                        operator: "In".to_owned(),
+                        values: Some(vec!["front".to_owned(), "edge".to_owned()]),
+                    },
+                    LabelSelectorRequirement {
+                        key: "canary".to_owned(),
+                        operator: "DoesNotExist".to_owned(),
+                        values: None,
+                    },
+                ]),
+            };
+            assert_eq!(
+                selector_string(&selector),
+                "app=web,tier in (front,edge),!canary"
+            );
+        }
+
+        #[test]
+        pub fn empty_selector_is_empty() {
+            assert_eq!(selector_string(&LabelSelector::default()), "");
+        }
+    }
+
+    fn deploy(name: &str, replicas: Option<i32>, status: Option<(i32, i32, i32)>) -> Deployment {
+        Deployment {
+            metadata: ObjectMeta {
+                name: Some(name.to_owned()),
+                ..Default::default()
+            },
+            spec: Some(DeploymentSpec {
+                replicas,
+                ..Default::default()
+            }),
+            status: status.map(|(ready, updated, available)| DeploymentStatus {
+                ready_replicas: Some(ready),
+                updated_replicas: Some(updated),
+                available_replicas: Some(available),
+                ..Default::default()
+            }),
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use super::channel::MessageSender;
+use super::client::ClientManager;
+use super::{Error, KubeError};
+use crate::KubeMessage;
+use futures::SinkExt;
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Status;
+use kube::api::{Api, AttachParams, AttachedProcess, TerminalSize};
+use kube::Client;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+
+// Starts bash when the image has it, as it is much nicer to use than sh
+const SHELL: [&str; 3] = [
+    "sh",
+    "-c",
+    "command -v bash >/dev/null && exec bash || exec sh",
+];
+
+pub enum ExecEvent {
+    Output(Vec<u8>),
+    /// The shell has exited, with how it exited
+    Exited(String),
+}
+
+/// What a command run to completion printed, and how it exited
+#[derive(Clone, PartialEq, Debug)]
+pub struct CommandOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// None when the pod did not report one
+    pub exit_code: Option<i32>,
+}
+
+/// An interactive shell in a pod. Dropping it ends the shell.
+pub struct ShellSession {
+    input: UnboundedSender<Vec<u8>>,
+    resize: UnboundedSender<(u16, u16)>,
+    handle: JoinHandle<()>,
+}
+
+impl ShellSession {
+    /// Sends keystrokes to the shell
+    pub fn write(&self, bytes: Vec<u8>) {
+        let _ = self.input.send(bytes);
+    }
+
+    pub fn resize(&self, cols: u16, rows: u16) {
+        let _ = self.resize.send((cols, rows));
+    }
+}
+
+impl Drop for ShellSession {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Opens a shell with a TTY in `container` of `pod`. Its output is sent to the UI
+/// tagged with `id`.
+pub fn open_shell(
+    clients: ClientManager,
+    namespace: String,
+    pod: String,
+    container: String,
+    id: u64,
+    tx: MessageSender,
+) -> ShellSession {
+    let (input, input_rx) = unbounded_channel();
+    let (resize, resize_rx) = unbounded_channel();
+    let handle = tokio::spawn(async move {
+        let exited = run_shell(
+            &clients, &namespace, &pod, &container, id, &tx, input_rx, resize_rx,
+        );
+        let msg = match exited.await {
+            Ok(status) => Ok(ExecEvent::Exited(status)),
+            Err(err) => Err(KubeError::new(&pod, err)),
+        };
+        let _ = tx.send(KubeMessage::Exec(id, msg));
+    });
+    ShellSession {
+        input,
+        resize,
+        handle,
+    }
+}
+
+/// Closes the connection to the pod when the session is dropped part way through
+struct Attached(AttachedProcess);
+
+impl Drop for Attached {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_shell(
+    clients: &ClientManager,
+    namespace: &str,
+    pod: &str,
+    container: &str,
+    id: u64,
+    tx: &MessageSender,
+    mut input_rx: UnboundedReceiver<Vec<u8>>,
+    mut resize_rx: UnboundedReceiver<(u16, u16)>,
+) -> Result<String, Error> {
+    let pods: Api<Pod> = Api::namespaced(clients.client().await?, namespace);
+    let params = AttachParams::interactive_tty().container(container);
+    let mut attached = Attached(pods.exec(pod, SHELL, &params).await?);
+
+    let mut stdout = attached.0.stdout();
+    let mut stdin = attached.0.stdin();
+    let mut terminal_size = attached.0.terminal_size();
+    let status = attached.0.take_status();
+
+    // Typing and resizing run alongside reading the output, and end with it
+    let writer = tokio::spawn(async move {
+        while let Some(bytes) = input_rx.recv().await {
+            let written = match stdin.as_mut() {
+                Some(stdin) => stdin.write_all(&bytes).await,
+                None => return,
+            };
+            if written.is_err() {
+                return;
+            }
+        }
+    });
+    let resizer = tokio::spawn(async move {
+        while let Some((width, height)) = resize_rx.recv().await {
+            if let Some(sender) = terminal_size.as_mut() {
+                let _ = sender.send(TerminalSize { width, height }).await;
+            }
+        }
+    });
+
+    if let Some(stdout) = stdout.as_mut() {
+        let mut buf = vec![0; 8192];
+        while let Ok(read) = stdout.read(&mut buf).await {
+            if read == 0 {
+                break;
+            }
+            let output = ExecEvent::Output(buf[..read].to_vec());
+            if tx.send(KubeMessage::Exec(id, Ok(output))).is_err() {
+                break;
+            }
+        }
+    }
+    writer.abort();
+    resizer.abort();
+
+    let status = match status {
+        Some(status) => status.await,
+        None => None,
+    };
+    Ok(status
+        .and_then(|s| s.message.or(s.status))
+        .unwrap_or("Shell exited".to_owned()))
+}
+
+/// Runs `command` in `pod` without a TTY or stdin, waiting for it to finish
+pub async fn run_command(
+    client: Client,
+    namespace: &str,
+    pod: &str,
+    container: Option<&str>,
+    command: &[String],
+) -> Result<CommandOutput, kube::Error> {
+    let pods: Api<Pod> = Api::namespaced(client, namespace);
+    let mut params = AttachParams::default();
+    if let Some(container) = container {
+        params = params.container(container);
+    }
+    let mut attached = Attached(pods.exec(pod, command.to_vec(), &params).await?);
+    let stdout = attached.0.stdout();
+    let stderr = attached.0.stderr();
+    let status = attached.0.take_status();
+
+    // Both are read at once, as a command blocks when either pipe fills up
+    let (stdout, stderr) = futures::join!(read_all(stdout), read_all(stderr));
+    let status = match status {
+        Some(status) => status.await,
+        None => None,
+    };
+    Ok(CommandOutput {
+        stdout,
+        stderr,
+        exit_code: status.as_ref().and_then(exit_code),
+    })
+}
+
+async fn read_all(stream: Option<impl AsyncReadExt + Unpin>) -> String {
+    let mut bytes = vec![];
+    if let Some(mut stream) = stream {
+        let _ = stream.read_to_end(&mut bytes).await;
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// The exit code from the status an exec ends with. Failures carry it as an
+/// `ExitCode` cause, success has none.
+fn exit_code(status: &Status) -> Option<i32> {
+    if status.status.as_deref() == Some("Success") {
+        return Some(0);
+    }
+    status
+        .details
+        .iter()
+        .flat_map(|d| d.causes.iter().flatten())
+        .find(|c| c.reason.as_deref() == Some("ExitCode"))
+        .and_then(|c| c.message.as_ref())
+        .and_then(|code| code.parse().ok())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(test)]
+    mod exit_code {
+        use super::*;
+        use k8s_openapi::apimachinery::pkg::apis::meta::v1::{StatusCause, StatusDetails};
+
+        #[test]
+        pub fn success_is_zero() {
+            let status = Status {
+                status: Some("Success".to_owned()),
+                ..Default::default()
+            };
+            assert_eq!(exit_code(&status), Some(0));
+        }
+
+        #[test]
+        pub fn reads_the_exit_code_cause() {
+            let status = Status {
+                status: Some("Failure".to_owned()),
+                reason: Some("NonZeroExitCode".to_owned()),
+                details: Some(StatusDetails {
+                    causes: Some(vec![StatusCause {
+                        reason: Some("ExitCode".to_owned()),
+                        message: Some("3".to_owned()),
+                        ..Default::default()
+                    }]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            assert_eq!(exit_code(&status), Some(3));
+        }
+
+        #[test]
+        pub fn other_failures_have_no_code() {
+            let status = Status {
+                status: Some("Failure".to_owned()),
+                message: Some("container not found".to_owned()),
+                ..Default::default()
+            };
+            assert_eq!(exit_code(&status), None);
+        }
+    }
+}
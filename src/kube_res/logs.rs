@@ -0,0 +1,288 @@
+use super::channel::MessageSender;
+use super::client::ClientManager;
+use super::deployments::deployment_pods;
+use super::{Error, KubeError};
+use crate::KubeMessage;
+use futures::{future::join_all, AsyncBufReadExt, StreamExt};
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::chrono::{DateTime, Utc};
+use kube::api::{Api, LogParams};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::task::JoinHandle;
+
+static NEXT_STREAM_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A unique id for a log stream, so that lines from a stream that has since been
+/// replaced can be told apart and ignored
+pub fn next_stream_id() -> u64 {
+    NEXT_STREAM_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// What to read logs from: one pod, or every pod of a deployment
+#[derive(Clone, PartialEq, Debug)]
+pub enum LogTarget {
+    Pod(String),
+    Deployment(String),
+}
+
+impl LogTarget {
+    pub fn name(&self) -> &str {
+        match self {
+            LogTarget::Pod(name) | LogTarget::Deployment(name) => name,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct LogLine {
+    pub pod: String,
+    pub time: Option<DateTime<Utc>>,
+    pub text: String,
+}
+
+/// A batch of log lines. `done` is set on the last one, once every stream has ended.
+pub struct LogChunk {
+    pub lines: Vec<LogLine>,
+    pub done: bool,
+}
+
+#[derive(Clone, Default)]
+pub struct LogOptions {
+    pub container: Option<String>,
+    pub follow: bool,
+    pub tail_lines: Option<i64>,
+    pub since_seconds: Option<i64>,
+    pub previous: bool,
+}
+
+impl LogOptions {
+    fn params(&self) -> LogParams {
+        LogParams {
+            container: self.container.clone(),
+            follow: self.follow,
+            tail_lines: self.tail_lines,
+            since_seconds: self.since_seconds,
+            previous: self.previous,
+            // Needed to interleave the logs of several pods
+            timestamps: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Streams the logs of `target` to the UI in batches tagged with `id`. The pods of a
+/// deployment are streamed concurrently.
+pub fn stream_logs(
+    clients: ClientManager,
+    namespace: String,
+    target: LogTarget,
+    options: LogOptions,
+    id: u64,
+    tx: MessageSender,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let pods = match target_pods(&clients, &namespace, &target).await {
+            Ok(pods) => pods,
+            Err(err) => {
+                let _ = tx.send(KubeMessage::Logs(
+                    id,
+                    Err(KubeError::new(target.name(), err)),
+                ));
+                vec![]
+            }
+        };
+        if let Ok(client) = clients.client().await {
+            let api: Api<Pod> = Api::namespaced(client, namespace.as_str());
+            join_all(
+                pods.iter()
+                    .map(|pod| stream_pod_logs(&api, pod, &options, id, &tx)),
+            )
+            .await;
+        }
+        let end = LogChunk {
+            lines: vec![],
+            done: true,
+        };
+        let _ = tx.send(KubeMessage::Logs(id, Ok(end)));
+    })
+}
+
+async fn target_pods(
+    clients: &ClientManager,
+    namespace: &str,
+    target: &LogTarget,
+) -> Result<Vec<String>, Error> {
+    match target {
+        LogTarget::Pod(pod) => Ok(vec![pod.clone()]),
+        LogTarget::Deployment(name) => {
+            Ok(deployment_pods(clients.client().await?, namespace, name).await?)
+        }
+    }
+}
+
+async fn stream_pod_logs(
+    api: &Api<Pod>,
+    pod: &str,
+    options: &LogOptions,
+    id: u64,
+    tx: &MessageSender,
+) {
+    let reader = match api.log_stream(pod, &options.params()).await {
+        Ok(reader) => reader,
+        Err(err) => {
+            let _ = tx.send(KubeMessage::Logs(id, Err(KubeError::new(pod, err))));
+            return;
+        }
+    };
+
+    // Lines that arrive together are sent together, so a burst of output is one
+    // message and one repaint rather than hundreds
+    let mut batches = reader.lines().ready_chunks(500);
+    while let Some(batch) = batches.next().await {
+        let lines = batch
+            .into_iter()
+            .filter_map(|line| line.ok())
+            .map(|line| parse_line(pod, &line))
+            .collect();
+        let chunk = LogChunk { lines, done: false };
+        if tx.send(KubeMessage::Logs(id, Ok(chunk))).is_err() {
+            return;
+        }
+    }
+}
+
+/// Splits the RFC 3339 timestamp kubernetes puts in front of each line off the text
+fn parse_line(pod: &str, line: &str) -> LogLine {
+    let parsed = line.split_once(' ').and_then(|(time, text)| {
+        let time = DateTime::parse_from_rfc3339(time).ok()?;
+        Some((time.with_timezone(&Utc), text))
+    });
+    let (time, text) = match parsed {
+        Some((time, text)) => (Some(time), text),
+        None => (None, line),
+    };
+    LogLine {
+        pod: pod.to_owned(),
+        time,
+        text: text.to_owned(),
+    }
+}
+
+/// Adds `new` to `lines`, keeping them in timestamp order. Lines mostly arrive in
+/// order, so this is usually a push onto the end.
+pub fn merge_lines(lines: &mut Vec<LogLine>, new: Vec<LogLine>) {
+    for line in new {
+        match line.time {
+            Some(time) if lines.last().and_then(|l| l.time).is_some_and(|t| t > time) => {
+                let at = lines.partition_point(|l| l.time.is_none_or(|t| t <= time));
+                lines.insert(at, line);
+            }
+            _ => lines.push(line),
+        }
+    }
+}
+
+/// Parses durations like `30s`, `15m`, `2h` or `1d` into seconds. A bare number is
+/// taken as seconds.
+pub fn parse_duration(input: &str) -> Option<i64> {
+    let input = input.trim();
+    let (number, unit) = match input.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&input[..i], c),
+        _ => (input, 's'),
+    };
+    let number: i64 = number.trim().parse().ok()?;
+    let multiplier = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        _ => return None,
+    };
+    Some(number * multiplier)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(test)]
+    mod parse_line {
+        use super::*;
+
+        #[test]
+        pub fn splits_timestamp_from_text() {
+            let line = parse_line("web-1", "2023-05-01T10:00:00.123456789Z GET /health 200");
+            assert_eq!(line.pod, "web-1");
+            assert_eq!(line.text, "GET /health 200");
+            assert_eq!(
+                line.time.map(|t| t.to_rfc3339()),
+                Some("2023-05-01T10:00:00.123456789+00:00".to_owned())
+            );
+        }
+
+        #[test]
+        pub fn keeps_lines_without_timestamp() {
+            let line = parse_line("web-1", "plain text");
+            assert_eq!(line.time, None);
+            assert_eq!(line.text, "plain text");
+        }
+    }
+
+    #[cfg(test)]
+    mod merge_lines {
+        use super::*;
+
+        #[test]
+        pub fn interleaves_pods_by_time() {
+            let mut lines = vec![
+                parse_line("a", "2023-05-01T10:00:00Z a1"),
+                parse_line("a", "2023-05-01T10:00:02Z a2"),
+            ];
+            merge_lines(
+                &mut lines,
+                vec![
+                    parse_line("b", "2023-05-01T10:00:01Z b1"),
+                    parse_line("b", "2023-05-01T10:00:03Z b2"),
+                ],
+            );
+            let texts: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+            assert_eq!(texts, vec!["a1", "b1", "a2", "b2"]);
+        }
+
+        #[test]
+        pub fn equal_times_keep_arrival_order() {
+            let mut lines = vec![
+                parse_line("a", "2023-05-01T10:00:01Z a1"),
+                parse_line("a", "2023-05-01T10:00:05Z a2"),
+            ];
+            merge_lines(&mut lines, vec![parse_line("b", "2023-05-01T10:00:01Z b1")]);
+            let texts: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+            assert_eq!(texts, vec!["a1", "b1", "a2"]);
+        }
+    }
+
+    #[cfg(test)]
+    mod parse_duration {
+        use super::*;
+
+        #[test]
+        pub fn parses_units() {
+            assert_eq!(parse_duration("30s"), Some(30));
+            assert_eq!(parse_duration("15m"), Some(900));
+            assert_eq!(parse_duration("2h"), Some(7200));
+            assert_eq!(parse_duration("1d"), Some(86400));
+        }
+
+        #[test]
+        pub fn bare_numbers_are_seconds() {
+            assert_eq!(parse_duration(" 45 "), Some(45));
+        }
+
+        #[test]
+        pub fn rejects_garbage() {
+            assert_eq!(parse_duration(""), None);
+            assert_eq!(parse_duration("10y"), None);
+            assert_eq!(parse_duration("abc"), None);
+        }
+    }
+}
@@ -1,9 +1,28 @@
 pub mod actions;
+pub mod browse;
+pub mod channel;
+pub mod client;
+pub mod clusters;
+pub mod contexts;
+pub mod deployments;
+pub mod exec;
+pub mod logs;
 pub mod namespaces;
 pub mod pods;
+pub mod port_forward;
+pub mod secrets;
 pub mod services;
+pub mod watch;
+use browse::{Applied, ObjectList};
+use contexts::KubeContexts;
 use eframe::egui::Color32;
-use kube::Error;
+use exec::{CommandOutput, ExecEvent};
+use kube::api::{ApiResource, DynamicObject};
+use kube::config::KubeconfigError;
+use logs::LogChunk;
+use pods::PodSummary;
+use port_forward::PortForward;
+use secrets::SecretValue;
 use std::fmt;
 
 #[derive(PartialEq, Clone, Debug)]
@@ -12,6 +31,8 @@ pub enum KubeStatus {
     Good,
     Bad(String),
     Suspicious(String),
+    /// The check itself failed, e.g. the request was forbidden or timed out
+    Error(String),
 }
 
 impl fmt::Display for KubeStatus {
@@ -21,13 +42,31 @@ impl fmt::Display for KubeStatus {
             KubeStatus::Good => write!(f, "All good here"),
             KubeStatus::Bad(msg) => write!(f, "Bad: {}", msg),
             KubeStatus::Suspicious(msg) => write!(f, "May have a problem: {}", msg),
+            KubeStatus::Error(msg) => write!(f, "Could not check: {}", msg),
         }
     }
 }
 
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ActionOutcome {
+    /// The action is still running, the results describe how far it got
+    InProgress,
+    /// The action needs the user to confirm what is listed in the results
+    NeedsConfirmation,
+    Done,
+    Failed,
+}
+
 pub struct ActionResult {
     pub name: String,
     pub results: String,
+    pub outcome: ActionOutcome,
+    /// Keys and values read from a secret, which the UI keeps masked until asked
+    pub secrets: Vec<SecretValue>,
+    /// What a command run by an `exec` action printed
+    pub output: Option<CommandOutput>,
+    /// The namespace and names of pods the user is asked to confirm the action on
+    pub pods: Option<(String, Vec<String>)>,
 }
 
 #[derive(PartialEq, Clone)]
@@ -56,6 +95,7 @@ impl KubeResource {
             KubeStatus::Good => Color32::GREEN,
             KubeStatus::Bad(_) => Color32::RED,
             KubeStatus::Suspicious(_) => Color32::YELLOW,
+            KubeStatus::Error(_) => Color32::LIGHT_RED,
         }
     }
 }
@@ -66,8 +106,162 @@ impl fmt::Display for KubeResource {
     }
 }
 
+/// What went wrong talking to kubernetes, including not getting as far as having a
+/// client because the kubeconfig could not be loaded
+#[derive(Debug)]
+pub enum Error {
+    Kube(kube::Error),
+    Kubeconfig(KubeconfigError),
+}
+
+impl Error {
+    /// A short, readable description of what went wrong
+    pub fn describe(&self) -> String {
+        match self {
+            Error::Kube(err) => describe_error(err),
+            Error::Kubeconfig(err) => format!("Could not load kubeconfig: {}", err),
+        }
+    }
+}
+
+impl From<kube::Error> for Error {
+    fn from(err: kube::Error) -> Self {
+        Error::Kube(err)
+    }
+}
+
+impl From<KubeconfigError> for Error {
+    fn from(err: KubeconfigError) -> Self {
+        Error::Kubeconfig(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe())
+    }
+}
+
+/// An error from kubernetes, tagged with the name of the resource or action it
+/// happened for so the UI can show it in the right place
+pub struct KubeError {
+    pub name: String,
+    pub error: Error,
+}
+
+impl KubeError {
+    pub fn new(name: &str, error: impl Into<Error>) -> Self {
+        Self {
+            name: name.to_owned(),
+            error: error.into(),
+        }
+    }
+
+    /// A short, readable description of what went wrong
+    pub fn describe(&self) -> String {
+        self.error.describe()
+    }
+}
+
+impl fmt::Display for KubeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.describe())
+    }
+}
+
+pub fn describe_error(err: &kube::Error) -> String {
+    match err {
+        kube::Error::Api(resp) => match resp.code {
+            401 => format!("Unauthorized, check your login: {}", resp.message),
+            403 => format!("Forbidden: {}", resp.message),
+            404 => format!("Not found: {}", resp.message),
+            408 | 504 => format!("Timed out: {}", resp.message),
+            _ => format!("{} ({}): {}", resp.reason, resp.code, resp.message),
+        },
+        kube::Error::HyperError(e) if e.is_timeout() => format!("Timed out: {}", e),
+        other => other.to_string(),
+    }
+}
+
 pub enum KubeMessage {
+    /// The cluster a (re)built client talks to, or why it could not be built
+    Client(Result<String, String>),
+    Contexts(Result<KubeContexts, String>),
     Namespaces(Result<Vec<String>, Error>),
-    Resource(Result<KubeResource, Error>),
-    Action(Result<ActionResult, Error>),
+    Resource(Result<KubeResource, KubeError>),
+    /// A resource checked in another cluster, tagged with the id of the check and the
+    /// kube context it came from
+    Cluster(u64, String, KubeResource),
+    /// Every pod in the watched namespace. Failures come as a `Resource` error.
+    Pods(Vec<PodSummary>),
+    Action(Result<ActionResult, KubeError>),
+    /// Log lines for the stream with the given id
+    Logs(u64, Result<LogChunk, KubeError>),
+    /// A port forward an action started, for the UI to list and stop
+    PortForward(PortForward),
+    /// Every kind of resource the resource browser can list, from the discovery with
+    /// the given id
+    Kinds(u64, Result<Vec<ApiResource>, Error>),
+    Objects(ObjectList),
+    /// One object fetched for the detail view with the given id
+    Object(u64, Result<Box<DynamicObject>, KubeError>),
+    /// An edit applied, or dry run, for the detail view with the given id
+    Applied(u64, Result<Applied, KubeError>),
+    /// A message from the status board's watches, tagged with their generation
+    Watched(u64, Box<KubeMessage>),
+    /// Output of, or the end of, the shell with the given id
+    Exec(u64, Result<ExecEvent, KubeError>),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(test)]
+    mod describe_error {
+        use super::*;
+        use kube::error::ErrorResponse;
+
+        fn api_error(code: u16, reason: &str, message: &str) -> kube::Error {
+            kube::Error::Api(ErrorResponse {
+                status: "Failure".to_owned(),
+                message: message.to_owned(),
+                reason: reason.to_owned(),
+                code,
+            })
+        }
+
+        #[test]
+        pub fn describes_forbidden() {
+            let err = api_error(403, "Forbidden", "pods is forbidden");
+            assert_eq!(describe_error(&err), "Forbidden: pods is forbidden");
+        }
+
+        #[test]
+        pub fn describes_not_found() {
+            let err = api_error(404, "NotFound", "secrets \"db\" not found");
+            assert_eq!(describe_error(&err), "Not found: secrets \"db\" not found");
+        }
+
+        #[test]
+        pub fn describes_timeout() {
+            let err = api_error(504, "Timeout", "the server was unable to respond");
+            assert_eq!(
+                describe_error(&err),
+                "Timed out: the server was unable to respond"
+            );
+        }
+
+        #[test]
+        pub fn includes_reason_for_other_api_errors() {
+            let err = api_error(409, "Conflict", "already exists");
+            assert_eq!(describe_error(&err), "Conflict (409): already exists");
+        }
+
+        #[test]
+        pub fn tags_errors_with_their_source() {
+            let err = KubeError::new("pod", api_error(403, "Forbidden", "nope"));
+            assert_eq!(err.to_string(), "pod: Forbidden: nope");
+        }
+    }
 }
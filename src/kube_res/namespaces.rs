@@ -1,17 +1,17 @@
+use super::channel::MessageSender;
+use super::client::ClientManager;
+use super::Error;
 use crate::KubeMessage;
 use k8s_openapi::api::core::v1::Namespace;
-use kube::{
-    api::{Api, ListParams},
-    Client,
-};
-use std::sync::mpsc::Sender;
+use kube::api::{Api, ListParams};
 
-pub fn get_namespaces(tx: Sender<KubeMessage>) {
+pub fn get_namespaces(clients: ClientManager, tx: MessageSender) {
     tokio::spawn(async move {
-        match Client::try_default().await {
+        match clients.client().await {
             Ok(client) => {
                 let namespaces: Api<Namespace> = Api::all(client);
-                let all = namespaces.list(&ListParams::default()).await.map(|list| {
+                let all = namespaces.list(&ListParams::default()).await;
+                let all = all.map_err(Error::from).map(|list| {
                     list.iter()
                         .map(|ns| ns.metadata.name.clone().unwrap_or("".to_owned()))
                         .collect::<Vec<String>>()
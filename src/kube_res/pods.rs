@@ -1,89 +1,340 @@
+use super::channel::MessageSender;
+use super::client::ClientManager;
+use super::watch::watch_namespaced;
+use super::{Error, KubeError};
+use crate::config::Thresholds;
 use crate::{KubeMessage, KubeResource, KubeStatus};
 use k8s_openapi::api::core::v1::Pod;
-use kube::{
-    api::{Api, ListParams, ObjectList},
-    {Client, Error},
-};
-use std::sync::mpsc::Sender;
-
-pub fn check_pods(namespace: String, tx: Sender<KubeMessage>) {
-    tokio::spawn(async move {
-        let msg = match Client::try_default().await {
-            Ok(client) => {
-                let pods_request: Api<Pod> = Api::namespaced(client, namespace.as_str());
-                let all_pods = pods_request.list(&ListParams::default()).await;
-                let pods = all_pods.map(|list| {
-                    let all_pods_vec: Vec<Pod> = list.iter().map(|p| p.clone()).collect();
-                    println!("got pods list! ns: {}", namespace);
-                    (all_pods_vec, only_bad_pods(&list))
-                });
-                pods_message(pods)
-            }
-            Err(err) => error(err),
-        };
-        match tx.send(msg) {
-            Ok(_) => {}
-            Err(e) => println!("Failed sending message about pods: {}", e),
-        };
-    });
+use k8s_openapi::chrono::{DateTime, Duration, Utc};
+use tokio::task::JoinHandle;
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct PodSummary {
+    pub name: String,
+    pub phase: String,
+    pub ready: String,
+    pub restarts: i32,
+    pub created: Option<DateTime<Utc>>,
+    pub node: String,
+    /// App containers first, so the first one is a sensible default for logs
+    pub containers: Vec<String>,
+    /// The deployment the pod belongs to, if any
+    pub deployment: Option<String>,
+    pub bad: bool,
+    pub suspicious: bool,
 }
 
-fn pods_message(pods: Result<(Vec<Pod>, Vec<Option<Pod>>), Error>) -> KubeMessage {
-    match pods {
-        Ok((all_pods, bad_pods)) => {
-            if bad_pods.iter().count() > 0 {
-                success(KubeStatus::Bad("One or more not ready".to_owned()))
-            } else if all_pods.iter().count() < 1 {
-                success(KubeStatus::Suspicious("No pods found".to_owned()))
+impl PodSummary {
+    /// Age of the pod in the short form kubectl uses, e.g. `3d` or `12m`
+    pub fn age(&self) -> String {
+        format_age(self.created)
+    }
+}
+
+/// Time since `created` in the short form kubectl uses, e.g. `3d` or `12m`
+pub fn format_age(created: Option<DateTime<Utc>>) -> String {
+    match created {
+        Some(created) => {
+            let secs = (Utc::now() - created).num_seconds().max(0);
+            if secs >= 86400 {
+                format!("{}d", secs / 86400)
+            } else if secs >= 3600 {
+                format!("{}h", secs / 3600)
+            } else if secs >= 60 {
+                format!("{}m", secs / 60)
             } else {
-                success(KubeStatus::Good)
+                format!("{}s", secs)
             }
         }
+        None => "-".to_owned(),
+    }
+}
+
+/// Watches the pods in `namespace`, sending the per-pod table and the overall pod
+/// status every time any of them change
+pub fn watch_pods(
+    clients: ClientManager,
+    namespace: String,
+    generation: u64,
+    thresholds: Thresholds,
+    tx: MessageSender,
+) -> JoinHandle<()> {
+    watch_namespaced(
+        clients,
+        namespace,
+        generation,
+        tx,
+        move |pods: Vec<Pod>| {
+            let bad_pods = only_bad_pods(&pods);
+            let summaries = pod_summaries(&pods, &bad_pods, &thresholds);
+            vec![
+                KubeMessage::Pods(summaries),
+                pods_message(Ok((pods, bad_pods)), &thresholds),
+            ]
+        },
+        error,
+    )
+}
+
+fn pods_message(
+    pods: Result<(Vec<Pod>, Vec<Option<Pod>>), Error>,
+    thresholds: &Thresholds,
+) -> KubeMessage {
+    match pods {
+        Ok((all_pods, bad_pods)) => success(status_of(&all_pods, &bad_pods, thresholds)),
         Err(err) => error(err),
     }
 }
 
-fn only_bad_pods(list: &ObjectList<Pod>) -> Vec<Option<Pod>> {
+/// Overall status of every pod in a namespace
+pub fn pods_status(pods: &[Pod], thresholds: &Thresholds) -> KubeStatus {
+    status_of(pods, &only_bad_pods(pods), thresholds)
+}
+
+fn status_of(all_pods: &[Pod], bad_pods: &[Option<Pod>], thresholds: &Thresholds) -> KubeStatus {
+    if !bad_pods.is_empty() {
+        let problems = problem_messages(bad_pods.iter().flatten());
+        KubeStatus::Bad(format!("One or more not ready: {}", problems.join("; ")))
+    } else if all_pods.is_empty() {
+        KubeStatus::Suspicious("No pods found".to_owned())
+    } else {
+        let mut warnings = problem_messages(all_pods.iter());
+        warnings.extend(
+            all_pods
+                .iter()
+                .filter_map(|pod| restart_warning(pod, thresholds, Utc::now())),
+        );
+        if warnings.is_empty() {
+            KubeStatus::Good
+        } else {
+            KubeStatus::Suspicious(warnings.join("; "))
+        }
+    }
+}
+
+fn problem_messages<'a>(pods: impl Iterator<Item = &'a Pod>) -> Vec<String> {
+    pods.filter_map(|pod| match pod_health(pod) {
+        KubeStatus::Bad(msg) | KubeStatus::Suspicious(msg) => Some(msg),
+        _ => None,
+    })
+    .collect()
+}
+
+fn only_bad_pods(list: &[Pod]) -> Vec<Option<Pod>> {
     list.iter()
-        .map(|pod| {
-            pod.status
-                .clone()
-                .map(|s| (pod, s.phase.unwrap_or("unknown".to_owned())))
-        })
-        .filter(|opt| {
-            if let Some((_pod, phase)) = opt {
-                phase != &"Running".to_owned() && phase != &"Succeeded".to_owned()
-            } else {
-                false
+        .filter(|pod| pod.status.is_some() && matches!(pod_health(pod), KubeStatus::Bad(_)))
+        .map(|pod| Some(pod.clone()))
+        .collect::<Vec<Option<Pod>>>()
+}
+
+/// Waiting reasons that mean a container will not start without intervention
+const BAD_WAITING_REASONS: [&str; 6] = [
+    "CrashLoopBackOff",
+    "ImagePullBackOff",
+    "ErrImagePull",
+    "InvalidImageName",
+    "CreateContainerError",
+    "CreateContainerConfigError",
+];
+
+/// Terminated reasons that mean a container died rather than finished
+const BAD_TERMINATED_REASONS: [&str; 3] = ["OOMKilled", "Error", "ContainerCannotRun"];
+
+/// Health of a single pod based on its phase and the state of its (init) containers.
+/// A container that is currently failing makes the pod `Bad`, while one that recovered
+/// from a failure (`last_state`) only makes it `Suspicious`.
+fn pod_health(pod: &Pod) -> KubeStatus {
+    let name = pod.metadata.name.clone().unwrap_or("unknown".to_owned());
+    let status = match &pod.status {
+        Some(s) => s,
+        None => return KubeStatus::Good,
+    };
+    let containers = status
+        .init_container_statuses
+        .iter()
+        .flatten()
+        .chain(status.container_statuses.iter().flatten());
+
+    let mut suspicious: Option<String> = None;
+    for container in containers {
+        let state = container.state.as_ref();
+        let waiting = state
+            .and_then(|s| s.waiting.as_ref())
+            .and_then(|w| w.reason.clone());
+        if let Some(reason) = waiting {
+            if BAD_WAITING_REASONS.contains(&reason.as_str()) {
+                return KubeStatus::Bad(format!("{}: {} is {}", name, container.name, reason));
             }
-        })
-        .map(|opt| {
-            if let Some((pod, _phase)) = opt {
-                Some(pod.clone())
-            } else {
-                None
+        }
+        let terminated = state
+            .and_then(|s| s.terminated.as_ref())
+            .and_then(|t| t.reason.clone());
+        if let Some(reason) = terminated {
+            if BAD_TERMINATED_REASONS.contains(&reason.as_str()) {
+                return KubeStatus::Bad(format!(
+                    "{}: {} terminated with {}",
+                    name, container.name, reason
+                ));
+            }
+        }
+        let last_terminated = container
+            .last_state
+            .as_ref()
+            .and_then(|s| s.terminated.as_ref())
+            .and_then(|t| t.reason.clone());
+        if let Some(reason) = last_terminated {
+            if suspicious.is_none() && BAD_TERMINATED_REASONS.contains(&reason.as_str()) {
+                suspicious = Some(format!(
+                    "{}: {} was last terminated with {}",
+                    name, container.name, reason
+                ));
+            }
+        }
+    }
+
+    let phase = status.phase.clone().unwrap_or("unknown".to_owned());
+    if phase != "Running" && phase != "Succeeded" {
+        KubeStatus::Bad(format!("{} is {}", name, phase))
+    } else if let Some(msg) = suspicious {
+        KubeStatus::Suspicious(msg)
+    } else {
+        KubeStatus::Good
+    }
+}
+
+/// Warning for a pod that restarted more than `max_restarts` times. When a
+/// `restart_window` is configured, restarts that all happened before it are ignored.
+fn restart_warning(pod: &Pod, thresholds: &Thresholds, now: DateTime<Utc>) -> Option<String> {
+    let max_restarts = thresholds.max_restarts?;
+    let containers = pod.status.as_ref()?.container_statuses.as_ref()?;
+    let restarts: i32 = containers.iter().map(|c| c.restart_count).sum();
+    if restarts <= max_restarts {
+        return None;
+    }
+    if let Some(window) = thresholds.restart_window {
+        let last_restart = containers
+            .iter()
+            .filter_map(|c| {
+                c.last_state
+                    .as_ref()?
+                    .terminated
+                    .as_ref()?
+                    .finished_at
+                    .clone()
+            })
+            .map(|t| t.0)
+            .max();
+        if let Some(last) = last_restart {
+            if now - last > Duration::seconds(window) {
+                return None;
+            }
+        }
+    }
+    let name = pod.metadata.name.clone().unwrap_or("unknown".to_owned());
+    Some(format!("{} restarted {} times", name, restarts))
+}
+
+/// One summary per pod, with the pods in `bad_pods` flagged and sorted to the top
+fn pod_summaries(
+    all_pods: &[Pod],
+    bad_pods: &[Option<Pod>],
+    thresholds: &Thresholds,
+) -> Vec<PodSummary> {
+    let bad_names: Vec<Option<String>> = bad_pods
+        .iter()
+        .flatten()
+        .map(|p| p.metadata.name.clone())
+        .collect();
+    let mut summaries: Vec<PodSummary> = all_pods
+        .iter()
+        .map(|pod| {
+            let status = pod.status.clone().unwrap_or_default();
+            let containers = status.container_statuses.unwrap_or_default();
+            let ready_count = containers.iter().filter(|c| c.ready).count();
+            PodSummary {
+                name: pod.metadata.name.clone().unwrap_or("unknown".to_owned()),
+                phase: status.phase.unwrap_or("Unknown".to_owned()),
+                ready: format!("{}/{}", ready_count, containers.len()),
+                restarts: containers.iter().map(|c| c.restart_count).sum(),
+                created: pod.metadata.creation_timestamp.clone().map(|t| t.0),
+                node: pod
+                    .spec
+                    .as_ref()
+                    .and_then(|s| s.node_name.clone())
+                    .unwrap_or("-".to_owned()),
+                containers: pod
+                    .spec
+                    .as_ref()
+                    .map(|s| {
+                        let init = s.init_containers.iter().flatten();
+                        s.containers
+                            .iter()
+                            .chain(init)
+                            .map(|c| c.name.clone())
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                deployment: deployment_of(pod),
+                bad: bad_names.contains(&pod.metadata.name),
+                suspicious: matches!(pod_health(pod), KubeStatus::Suspicious(_))
+                    || restart_warning(pod, thresholds, Utc::now()).is_some(),
             }
         })
-        .collect::<Vec<Option<Pod>>>()
+        .collect();
+    summaries.sort_by(|a, b| {
+        b.bad
+            .cmp(&a.bad)
+            .then_with(|| b.suspicious.cmp(&a.suspicious))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    summaries
 }
 
-fn success(status: KubeStatus) -> KubeMessage {
-    KubeMessage::Resource(Ok(KubeResource {
+/// Whether kubernetes considers the pod ready to serve traffic
+pub fn is_ready(pod: &Pod) -> bool {
+    pod.status
+        .iter()
+        .flat_map(|s| s.conditions.iter().flatten())
+        .any(|c| c.type_ == "Ready" && c.status == "True")
+}
+
+/// Name of the deployment owning `pod`. Deployments name their replica sets
+/// `<deployment>-<pod-template-hash>`, so the hash is stripped off the owner's name.
+fn deployment_of(pod: &Pod) -> Option<String> {
+    let hash = pod.metadata.labels.as_ref()?.get("pod-template-hash")?;
+    pod.metadata
+        .owner_references
+        .iter()
+        .flatten()
+        .find(|owner| owner.kind == "ReplicaSet")
+        .and_then(|owner| owner.name.strip_suffix(&format!("-{}", hash)))
+        .map(|name| name.to_owned())
+}
+
+pub fn resource(status: KubeStatus) -> KubeResource {
+    KubeResource {
         name: "pod".to_owned(),
         display: "Pods".to_owned(),
         status,
-    }))
+    }
+}
+
+fn success(status: KubeStatus) -> KubeMessage {
+    KubeMessage::Resource(Ok(resource(status)))
 }
 
 fn error(err: Error) -> KubeMessage {
-    KubeMessage::Resource(Err(err))
+    KubeMessage::Resource(Err(KubeError::new("pod", err)))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
-    use k8s_openapi::api::core::v1::{Pod, PodStatus};
+    use k8s_openapi::api::core::v1::{
+        ContainerState, ContainerStateTerminated, ContainerStateWaiting, ContainerStatus, Pod,
+        PodStatus,
+    };
     use kube::api::ObjectList;
     use std::io::Error as IOError;
     use std::io::ErrorKind;
@@ -97,13 +348,13 @@ mod test {
                 vec![pod(Some("Running".to_owned()))],
                 vec![Some(pod(Some("Bad".to_owned())))],
             ));
-            let msg = pods_message(pods);
+            let msg = pods_message(pods, &Thresholds::default());
 
             match msg {
                 KubeMessage::Resource(Ok(res)) => {
                     assert_eq!(
                         res.status,
-                        KubeStatus::Bad("One or more not ready".to_owned())
+                        KubeStatus::Bad("One or more not ready: unknown is Bad".to_owned())
                     );
                 }
                 _ => panic!("bad pods should result in a Bad message"),
@@ -113,7 +364,7 @@ mod test {
         #[test]
         pub fn is_sus_when_no_pods() {
             let pods = Ok((vec![], vec![]));
-            let msg = pods_message(pods);
+            let msg = pods_message(pods, &Thresholds::default());
 
             match msg {
                 KubeMessage::Resource(Ok(res)) => {
@@ -129,7 +380,7 @@ mod test {
         #[test]
         pub fn is_good_when_no_bad_pods() {
             let pods = Ok((vec![pod(Some("Running".to_owned()))], vec![]));
-            let msg = pods_message(pods);
+            let msg = pods_message(pods, &Thresholds::default());
 
             match msg {
                 KubeMessage::Resource(Ok(res)) => {
@@ -139,16 +390,36 @@ mod test {
             }
         }
 
+        #[test]
+        pub fn is_sus_when_container_recovered_from_failure() {
+            let mut p = named_pod("app", Some("Running".to_owned()));
+            set_containers(&mut p, vec![last_terminated("web", "OOMKilled")]);
+            let msg = pods_message(Ok((vec![p], vec![])), &Thresholds::default());
+
+            match msg {
+                KubeMessage::Resource(Ok(res)) => {
+                    assert_eq!(
+                        res.status,
+                        KubeStatus::Suspicious(
+                            "app: web was last terminated with OOMKilled".to_owned()
+                        )
+                    );
+                }
+                _ => panic!("Recovered containers should result in Suspicious message"),
+            }
+        }
+
         #[test]
         pub fn is_error_when_error() {
             let io_error = IOError::new(ErrorKind::NotFound, "borked");
-            let pods = Err(Error::ReadEvents(io_error));
-            let msg = pods_message(pods);
+            let pods = Err(kube::Error::ReadEvents(io_error).into());
+            let msg = pods_message(pods, &Thresholds::default());
 
             match msg {
                 KubeMessage::Resource(Err(err)) => {
+                    assert_eq!(err.name, "pod");
                     assert_eq!(
-                        err.to_string(),
+                        err.error.to_string(),
                         "Error reading events stream: borked".to_owned()
                     );
                 }
@@ -157,6 +428,201 @@ mod test {
         }
     }
 
+    #[cfg(test)]
+    mod pod_health {
+        use super::*;
+
+        #[test]
+        pub fn running_pod_with_healthy_containers_is_good() {
+            let mut p = named_pod("app", Some("Running".to_owned()));
+            set_containers(&mut p, vec![container("web", true, 0)]);
+            assert_eq!(pod_health(&p), KubeStatus::Good);
+        }
+
+        #[test]
+        pub fn crash_looping_container_is_bad() {
+            let mut p = named_pod("app", Some("Running".to_owned()));
+            set_containers(&mut p, vec![waiting("web", "CrashLoopBackOff")]);
+            assert_eq!(
+                pod_health(&p),
+                KubeStatus::Bad("app: web is CrashLoopBackOff".to_owned())
+            );
+        }
+
+        #[test]
+        pub fn init_container_image_pull_failure_is_bad() {
+            let mut p = named_pod("app", Some("Pending".to_owned()));
+            if let Some(status) = p.status.as_mut() {
+                status.init_container_statuses = Some(vec![waiting("migrate", "ImagePullBackOff")]);
+            }
+            assert_eq!(
+                pod_health(&p),
+                KubeStatus::Bad("app: migrate is ImagePullBackOff".to_owned())
+            );
+        }
+
+        #[test]
+        pub fn oom_killed_container_is_bad() {
+            let mut p = named_pod("app", Some("Running".to_owned()));
+            let mut c = container("web", false, 1);
+            c.state = Some(ContainerState {
+                terminated: Some(ContainerStateTerminated {
+                    reason: Some("OOMKilled".to_owned()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            });
+            set_containers(&mut p, vec![c]);
+            assert_eq!(
+                pod_health(&p),
+                KubeStatus::Bad("app: web terminated with OOMKilled".to_owned())
+            );
+        }
+
+        #[test]
+        pub fn recovered_container_is_suspicious() {
+            let mut p = named_pod("app", Some("Running".to_owned()));
+            set_containers(&mut p, vec![last_terminated("web", "Error")]);
+            assert_eq!(
+                pod_health(&p),
+                KubeStatus::Suspicious("app: web was last terminated with Error".to_owned())
+            );
+        }
+
+        #[test]
+        pub fn pending_pod_is_bad() {
+            let p = named_pod("app", Some("Pending".to_owned()));
+            assert_eq!(pod_health(&p), KubeStatus::Bad("app is Pending".to_owned()));
+        }
+    }
+
+    #[cfg(test)]
+    mod restart_warning {
+        use super::*;
+        use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+
+        fn thresholds(max_restarts: i32, restart_window: Option<i64>) -> Thresholds {
+            Thresholds {
+                max_restarts: Some(max_restarts),
+                restart_window,
+            }
+        }
+
+        fn restarted_pod(restart_count: i32, minutes_ago: i64) -> Pod {
+            let mut c = last_terminated("web", "Completed");
+            c.restart_count = restart_count;
+            if let Some(terminated) = c.last_state.as_mut().and_then(|s| s.terminated.as_mut()) {
+                terminated.finished_at = Some(Time(Utc::now() - Duration::minutes(minutes_ago)));
+            }
+            let mut p = named_pod("app", Some("Running".to_owned()));
+            set_containers(&mut p, vec![c]);
+            p
+        }
+
+        #[test]
+        pub fn nothing_when_no_threshold_configured() {
+            let p = restarted_pod(50, 1);
+            assert_eq!(
+                restart_warning(&p, &Thresholds::default(), Utc::now()),
+                None
+            );
+        }
+
+        #[test]
+        pub fn warns_when_over_threshold() {
+            let p = restarted_pod(6, 1);
+            assert_eq!(
+                restart_warning(&p, &thresholds(5, None), Utc::now()),
+                Some("app restarted 6 times".to_owned())
+            );
+        }
+
+        #[test]
+        pub fn nothing_when_at_threshold() {
+            let p = restarted_pod(5, 1);
+            assert_eq!(restart_warning(&p, &thresholds(5, None), Utc::now()), None);
+        }
+
+        #[test]
+        pub fn ignores_restarts_outside_window() {
+            let p = restarted_pod(6, 120);
+            assert_eq!(
+                restart_warning(&p, &thresholds(5, Some(3600)), Utc::now()),
+                None
+            );
+        }
+
+        #[test]
+        pub fn warns_for_restarts_inside_window() {
+            let p = restarted_pod(6, 10);
+            assert_eq!(
+                restart_warning(&p, &thresholds(5, Some(3600)), Utc::now()),
+                Some("app restarted 6 times".to_owned())
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod pod_summaries {
+        use super::*;
+
+        #[test]
+        pub fn bad_pods_are_flagged_and_sorted_first() {
+            let good = named_pod("a-good", Some("Running".to_owned()));
+            let bad = named_pod("b-bad", Some("Pending".to_owned()));
+            let summaries =
+                pod_summaries(&[good, bad.clone()], &[Some(bad)], &Thresholds::default());
+
+            assert_eq!(summaries[0].name, "b-bad");
+            assert!(summaries[0].bad);
+            assert_eq!(summaries[1].name, "a-good");
+            assert!(!summaries[1].bad);
+        }
+
+        #[test]
+        pub fn counts_ready_containers_and_restarts() {
+            let mut p = named_pod("app", Some("Running".to_owned()));
+            set_containers(
+                &mut p,
+                vec![container("web", true, 2), container("sidecar", false, 3)],
+            );
+            let summaries = pod_summaries(&[p], &[], &Thresholds::default());
+
+            assert_eq!(summaries[0].ready, "1/2");
+            assert_eq!(summaries[0].restarts, 5);
+            assert_eq!(summaries[0].phase, "Running");
+        }
+    }
+
+    #[cfg(test)]
+    mod deployment_of {
+        use super::*;
+        use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+
+        #[test]
+        pub fn strips_template_hash_from_replica_set() {
+            let mut p = named_pod("web-5d9c7b-x2x7k", Some("Running".to_owned()));
+            p.metadata.labels = Some(
+                [("pod-template-hash".to_owned(), "5d9c7b".to_owned())]
+                    .into_iter()
+                    .collect(),
+            );
+            p.metadata.owner_references = Some(vec![OwnerReference {
+                kind: "ReplicaSet".to_owned(),
+                name: "web-5d9c7b".to_owned(),
+                ..Default::default()
+            }]);
+
+            assert_eq!(deployment_of(&p), Some("web".to_owned()));
+        }
+
+        #[test]
+        pub fn is_none_for_bare_pods() {
+            let p = named_pod("debug", Some("Running".to_owned()));
+            assert_eq!(deployment_of(&p), None);
+        }
+    }
+
     #[cfg(test)]
     mod only_bad_pods {
         use super::*;
@@ -173,7 +639,7 @@ mod test {
             };
 
             assert_eq!(
-                only_bad_pods(&pods).iter().count(),
+                only_bad_pods(&pods.items).iter().count(),
                 2,
                 "Number of bad pods was not correct"
             );
@@ -189,7 +655,18 @@ mod test {
                 ],
             };
 
-            assert_eq!(only_bad_pods(&pods).iter().count(), 0);
+            assert_eq!(only_bad_pods(&pods.items).iter().count(), 0);
+        }
+
+        #[test]
+        pub fn running_pods_with_failing_containers_are_bad() {
+            let mut crashing = pod(Some("Running".to_owned()));
+            set_containers(&mut crashing, vec![waiting("web", "CrashLoopBackOff")]);
+            let pods = ObjectList {
+                metadata: Default::default(),
+                items: vec![pod(Some("Running".to_owned())), crashing],
+            };
+            assert_eq!(only_bad_pods(&pods.items).iter().count(), 1);
         }
 
         #[test]
@@ -198,7 +675,52 @@ mod test {
                 metadata: Default::default(),
                 items: vec![pod(Some("Running".to_owned())), pod(None)],
             };
-            assert_eq!(only_bad_pods(&pods).iter().count(), 1);
+            assert_eq!(only_bad_pods(&pods.items).iter().count(), 1);
+        }
+    }
+
+    fn named_pod(name: &str, phase: Option<String>) -> Pod {
+        let mut p = pod(phase);
+        p.metadata.name = Some(name.to_owned());
+        p
+    }
+
+    fn container(name: &str, ready: bool, restart_count: i32) -> ContainerStatus {
+        ContainerStatus {
+            name: name.to_owned(),
+            ready,
+            restart_count,
+            ..Default::default()
+        }
+    }
+
+    fn waiting(name: &str, reason: &str) -> ContainerStatus {
+        let mut c = container(name, false, 0);
+        c.state = Some(ContainerState {
+            waiting: Some(ContainerStateWaiting {
+                reason: Some(reason.to_owned()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        c
+    }
+
+    fn last_terminated(name: &str, reason: &str) -> ContainerStatus {
+        let mut c = container(name, true, 1);
+        c.last_state = Some(ContainerState {
+            terminated: Some(ContainerStateTerminated {
+                reason: Some(reason.to_owned()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        c
+    }
+
+    fn set_containers(p: &mut Pod, containers: Vec<ContainerStatus>) {
+        if let Some(status) = p.status.as_mut() {
+            status.container_statuses = Some(containers);
         }
     }
 
@@ -0,0 +1,314 @@
+use super::describe_error;
+use super::pods::is_ready;
+use k8s_openapi::api::core::v1::{Pod, Service, ServicePort};
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use kube::api::{Api, ListParams};
+use kube::Client;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// The pod and port a forward connects to
+#[derive(Clone, PartialEq, Debug)]
+pub struct ForwardTarget {
+    pub pod: String,
+    pub port: u16,
+}
+
+/// A running port forward. Dropping it stops it, along with its open connections.
+pub struct PortForward {
+    pub name: String,
+    pub target: ForwardTarget,
+    pub local_addr: SocketAddr,
+    bytes: Arc<AtomicU64>,
+    last_error: Arc<Mutex<Option<String>>>,
+    handle: JoinHandle<()>,
+}
+
+impl PortForward {
+    /// Bytes sent and received over every connection so far
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Why the most recent connection could not be forwarded, if it could not
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().ok().and_then(|e| e.clone())
+    }
+}
+
+impl Drop for PortForward {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Resolves `resource` to the pod and port to forward to. `service/<name>` picks a
+/// ready pod behind the service and maps the service port to its target port,
+/// anything else is taken as a pod name.
+pub async fn resolve_target(
+    client: Client,
+    namespace: &str,
+    resource: &str,
+    port: u16,
+) -> Result<ForwardTarget, String> {
+    let service = match resource.split_once('/') {
+        Some(("service", name)) | Some(("svc", name)) => name,
+        Some(("pod", name)) | Some(("po", name)) => {
+            return Ok(ForwardTarget {
+                pod: name.to_owned(),
+                port,
+            })
+        }
+        _ => {
+            return Ok(ForwardTarget {
+                pod: resource.to_owned(),
+                port,
+            })
+        }
+    };
+    let services: Api<Service> = Api::namespaced(client.clone(), namespace);
+    let spec = services
+        .get(service)
+        .await
+        .map_err(|e| describe_error(&e))?
+        .spec
+        .unwrap_or_default();
+    let selector = spec.selector.unwrap_or_default();
+    if selector.is_empty() {
+        return Err(format!("Service {} has no pod selector", service));
+    }
+    let selector: Vec<String> = selector
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+    let pods: Api<Pod> = Api::namespaced(client, namespace);
+    let pod = pods
+        .list(&ListParams::default().labels(&selector.join(",")))
+        .await
+        .map_err(|e| describe_error(&e))?
+        .items
+        .into_iter()
+        .find(is_ready)
+        .ok_or(format!("Service {} has no ready pods", service))?;
+    Ok(ForwardTarget {
+        port: target_port(&spec.ports.unwrap_or_default(), &pod, port),
+        pod: pod.metadata.name.unwrap_or_default(),
+    })
+}
+
+/// The pod port a service port sends traffic to. Named target ports are looked up in
+/// the pod's containers.
+fn target_port(ports: &[ServicePort], pod: &Pod, port: u16) -> u16 {
+    let target = ports
+        .iter()
+        .find(|p| p.port == port as i32)
+        .and_then(|p| p.target_port.clone());
+    match target {
+        Some(IntOrString::Int(target)) => target as u16,
+        Some(IntOrString::String(name)) => pod
+            .spec
+            .iter()
+            .flat_map(|s| s.containers.iter())
+            .flat_map(|c| c.ports.iter().flatten())
+            .find(|p| p.name.as_deref() == Some(name.as_str()))
+            .map(|p| p.container_port as u16)
+            .unwrap_or(port),
+        None => port,
+    }
+}
+
+/// Listens on `local_port` on localhost, forwarding each connection to `target`.
+/// A `local_port` of 0 picks a free one.
+pub async fn start(
+    name: String,
+    client: Client,
+    namespace: &str,
+    target: ForwardTarget,
+    local_port: u16,
+) -> Result<PortForward, String> {
+    let listener = TcpListener::bind(("127.0.0.1", local_port))
+        .await
+        .map_err(|e| format!("Could not listen on port {}: {}", local_port, e))?;
+    let local_addr = listener.local_addr().map_err(|e| e.to_string())?;
+    let bytes = Arc::new(AtomicU64::new(0));
+    let last_error = Arc::new(Mutex::new(None));
+    let pods: Api<Pod> = Api::namespaced(client, namespace);
+    let handle = tokio::spawn(accept(
+        listener,
+        pods,
+        target.clone(),
+        bytes.clone(),
+        last_error.clone(),
+    ));
+    Ok(PortForward {
+        name,
+        target,
+        local_addr,
+        bytes,
+        last_error,
+        handle,
+    })
+}
+
+async fn accept(
+    listener: TcpListener,
+    pods: Api<Pod>,
+    target: ForwardTarget,
+    bytes: Arc<AtomicU64>,
+    last_error: Arc<Mutex<Option<String>>>,
+) {
+    // Dropped, and so closed, along with this task when the forward is stopped
+    let mut connections = Connections(vec![]);
+    while let Ok((conn, _)) = listener.accept().await {
+        connections.0.retain(|c| !c.is_finished());
+        connections.0.push(tokio::spawn(forward_connection(
+            conn,
+            pods.clone(),
+            target.clone(),
+            bytes.clone(),
+            last_error.clone(),
+        )));
+    }
+}
+
+struct Connections(Vec<JoinHandle<()>>);
+
+impl Drop for Connections {
+    fn drop(&mut self) {
+        for connection in self.0.iter() {
+            connection.abort();
+        }
+    }
+}
+
+async fn forward_connection(
+    conn: TcpStream,
+    pods: Api<Pod>,
+    target: ForwardTarget,
+    bytes: Arc<AtomicU64>,
+    last_error: Arc<Mutex<Option<String>>>,
+) {
+    let set_error = |err: String| {
+        if let Ok(mut last) = last_error.lock() {
+            *last = Some(err);
+        }
+    };
+    let mut forwarder = match pods.portforward(&target.pod, &[target.port]).await {
+        Ok(forwarder) => forwarder,
+        Err(err) => return set_error(describe_error(&err)),
+    };
+    let mut upstream = match forwarder.take_stream(target.port) {
+        Some(upstream) => upstream,
+        None => return set_error(format!("Port {} was not forwarded", target.port)),
+    };
+    let mut local = Counted { inner: conn, bytes };
+    if let Err(err) = tokio::io::copy_bidirectional(&mut local, &mut upstream).await {
+        set_error(err.to_string());
+    }
+    drop(upstream);
+    let _ = forwarder.join().await;
+}
+
+/// Counts the bytes read from and written to a stream
+struct Counted<S> {
+    inner: S,
+    bytes: Arc<AtomicU64>,
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for Counted<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let read = buf.filled().len() - before;
+        self.bytes.fetch_add(read as u64, Ordering::Relaxed);
+        poll
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Counted<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = &poll {
+            self.bytes.fetch_add(*written as u64, Ordering::Relaxed);
+        }
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(test)]
+    mod target_port {
+        use super::*;
+        use k8s_openapi::api::core::v1::{Container, ContainerPort, PodSpec};
+
+        fn service_port(port: i32, target: Option<IntOrString>) -> ServicePort {
+            ServicePort {
+                port,
+                target_port: target,
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        pub fn maps_numeric_target_ports() {
+            let ports = vec![service_port(80, Some(IntOrString::Int(8080)))];
+            assert_eq!(target_port(&ports, &Pod::default(), 80), 8080);
+        }
+
+        #[test]
+        pub fn looks_up_named_target_ports() {
+            let ports = vec![service_port(
+                80,
+                Some(IntOrString::String("http".to_owned())),
+            )];
+            let pod = Pod {
+                spec: Some(PodSpec {
+                    containers: vec![Container {
+                        name: "web".to_owned(),
+                        ports: Some(vec![ContainerPort {
+                            name: Some("http".to_owned()),
+                            container_port: 3000,
+                            ..Default::default()
+                        }]),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            assert_eq!(target_port(&ports, &pod, 80), 3000);
+        }
+
+        #[test]
+        pub fn unknown_ports_pass_through() {
+            let ports = vec![service_port(80, None)];
+            assert_eq!(target_port(&ports, &Pod::default(), 9090), 9090);
+        }
+    }
+}
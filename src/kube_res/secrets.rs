@@ -0,0 +1,223 @@
+use base64::Engine;
+use serde_json::Value as JsonValue;
+use serde_yaml::Value as YamlValue;
+use x509_parser::pem::Pem;
+
+// How many bytes of a binary value are shown as hex
+const HEX_PREVIEW: usize = 16;
+
+/// One key of a secret, with its value as stored and as shown to the user
+pub struct SecretValue {
+    pub key: String,
+    pub display: String,
+    /// The value as stored, in base64 when it is not text
+    pub raw: String,
+    pub binary: bool,
+}
+
+impl SecretValue {
+    pub fn new(key: &str, bytes: &[u8]) -> SecretValue {
+        let (raw, binary) = match std::str::from_utf8(bytes) {
+            Ok(text) => (text.to_owned(), false),
+            Err(_) => (
+                base64::engine::general_purpose::STANDARD.encode(bytes),
+                true,
+            ),
+        };
+        SecretValue {
+            key: key.to_owned(),
+            display: decode_value(bytes),
+            raw,
+            binary,
+        }
+    }
+}
+
+/// Renders a secret value for reading based on what it looks like: certificates get
+/// their subject and expiry, JSON and YAML are pretty-printed and binary data is
+/// summarised
+pub fn decode_value(bytes: &[u8]) -> String {
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text.trim(),
+        Err(_) => return binary_summary(bytes),
+    };
+    if text.starts_with("-----BEGIN") {
+        if let Some(pem) = describe_pem(bytes) {
+            return pem;
+        }
+    }
+    if text.starts_with('{') || text.starts_with('[') {
+        if let Ok(json) = serde_json::from_str::<JsonValue>(text) {
+            let pretty = serde_json::to_string_pretty(&json).unwrap_or(text.to_owned());
+            return match docker_registries(&json) {
+                Some(registries) => format!("{}\n{}", registries, pretty),
+                None => pretty,
+            };
+        }
+    }
+    // Any single line is valid YAML, so only multi-line documents are treated as such
+    if text.contains('\n') {
+        if let Ok(yaml) = serde_yaml::from_str::<YamlValue>(text) {
+            if yaml.is_mapping() || yaml.is_sequence() {
+                if let Ok(pretty) = serde_yaml::to_string(&yaml) {
+                    return pretty.trim_end().to_owned();
+                }
+            }
+        }
+    }
+    text.to_owned()
+}
+
+fn binary_summary(bytes: &[u8]) -> String {
+    let hex: Vec<String> = bytes
+        .iter()
+        .take(HEX_PREVIEW)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let more = if bytes.len() > HEX_PREVIEW {
+        " ..."
+    } else {
+        ""
+    };
+    format!("<binary, {} bytes> {}{}", bytes.len(), hex.join(" "), more)
+}
+
+/// One line per PEM block. Certificates show who they are for and when they expire,
+/// anything else (like keys) only its type.
+fn describe_pem(bytes: &[u8]) -> Option<String> {
+    let mut lines = vec![];
+    for pem in Pem::iter_from_buffer(bytes) {
+        let pem = pem.ok()?;
+        if pem.label != "CERTIFICATE" {
+            lines.push(format!("{} ({} bytes)", pem.label, pem.contents.len()));
+            continue;
+        }
+        let cert = pem.parse_x509().ok()?;
+        let validity = cert.validity();
+        let expiry = if validity.time_to_expiration().is_some() {
+            format!("expires {}", validity.not_after)
+        } else {
+            format!("EXPIRED {}", validity.not_after)
+        };
+        lines.push(format!("Certificate for {}, {}", cert.subject(), expiry));
+    }
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+/// For dockerconfigjson, a `registry: user` line per registry, with the user taken
+/// from the base64 `auth` when there is no `username`
+fn docker_registries(json: &JsonValue) -> Option<String> {
+    let auths = json.get("auths")?.as_object()?;
+    let lines: Vec<String> = auths
+        .iter()
+        .map(|(registry, auth)| {
+            let user = auth
+                .get("username")
+                .and_then(|u| u.as_str())
+                .map(|u| u.to_owned())
+                .or_else(|| {
+                    let encoded = auth.get("auth")?.as_str()?;
+                    let decoded = base64::engine::general_purpose::STANDARD
+                        .decode(encoded)
+                        .ok()?;
+                    let decoded = String::from_utf8(decoded).ok()?;
+                    decoded.split_once(':').map(|(user, _)| user.to_owned())
+                })
+                .unwrap_or("unknown user".to_owned());
+            format!("Registry {}: {}", registry, user)
+        })
+        .collect();
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(test)]
+    mod decode_value {
+        use super::*;
+
+        const CERT: &str = "-----BEGIN CERTIFICATE-----
+MIIBhjCCASugAwIBAgIUKK3p7h1KMaMzOCBV+OlTK4aoUWUwCgYIKoZIzj0EAwIw
+FzEVMBMGA1UEAwwMdGVzdC5leGFtcGxlMCAXDTI2MTAxODA2MjAyOVoYDzIxMjYw
+OTI0MDYyMDI5WjAXMRUwEwYDVQQDDAx0ZXN0LmV4YW1wbGUwWTATBgcqhkjOPQIB
+BggqhkjOPQMBBwNCAASji/RKegb51qnsjzWk0fu5WwW+eRnJlA66tkALle9mrXGt
+1Wlq11G75y72WjSDPksTkzGTuPUYXfLfljrRcHevo1MwUTAdBgNVHQ4EFgQU6fIG
+PYbA+u8I7jfhyjTXZXoYbsowHwYDVR0jBBgwFoAU6fIGPYbA+u8I7jfhyjTXZXoY
+bsowDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNJADBGAiEA97iMIMBaLs5n
+NVkC0ndIVLTj1S1eMK96xuhhNLLA6s8CIQDDuMWbZGfFvzzGS9VSEtHG8dJUntW/
+kk1lbW3VboGeqQ==
+-----END CERTIFICATE-----
+";
+
+        #[test]
+        pub fn plain_text_is_unchanged() {
+            assert_eq!(decode_value(b"hunter2"), "hunter2");
+        }
+
+        #[test]
+        pub fn pretty_prints_json() {
+            assert_eq!(
+                decode_value(br#"{"user":"app","port":5432}"#),
+                "{\n  \"user\": \"app\",\n  \"port\": 5432\n}"
+            );
+        }
+
+        #[test]
+        pub fn pretty_prints_yaml() {
+            assert_eq!(
+                decode_value(b"user:   app\nport:  5432\n"),
+                "user: app\nport: 5432"
+            );
+        }
+
+        #[test]
+        pub fn summarises_binary() {
+            assert_eq!(
+                decode_value(&[0xff, 0x00, 0x10]),
+                "<binary, 3 bytes> ff 00 10"
+            );
+        }
+
+        #[test]
+        pub fn describes_certificates() {
+            let described = decode_value(CERT.as_bytes());
+            assert!(described.starts_with("Certificate for CN=test.example, expires "));
+            assert!(described.contains("2126"));
+        }
+
+        #[test]
+        pub fn lists_docker_registries() {
+            // "robot:secret" in base64
+            let config = br#"{"auths":{"ghcr.io":{"auth":"cm9ib3Q6c2VjcmV0"}}}"#;
+            let described = decode_value(config);
+            assert!(described.starts_with("Registry ghcr.io: robot\n{"));
+        }
+    }
+
+    #[cfg(test)]
+    mod secret_value {
+        use super::*;
+
+        #[test]
+        pub fn keeps_the_raw_value_next_to_the_display() {
+            let value = SecretValue::new("config", b"{\"user\":\"app\"}");
+            assert_eq!(value.key, "config");
+            assert_eq!(value.display, "{\n  \"user\": \"app\"\n}");
+            assert_eq!(value.raw, "{\"user\":\"app\"}");
+            assert!(!value.binary);
+        }
+
+        #[test]
+        pub fn keeps_binary_values_as_base64() {
+            let value = SecretValue::new("keystore", &[0xff, 0x00, 0x10]);
+            assert_eq!(value.raw, "/wAQ");
+            assert!(value.binary);
+        }
+    }
+}
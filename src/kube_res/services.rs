@@ -1,46 +1,42 @@
+use super::channel::MessageSender;
+use super::client::ClientManager;
+use super::watch::watch_namespaced;
+use super::{Error, KubeError};
 use crate::{KubeMessage, KubeResource, KubeStatus};
 use k8s_openapi::api::core::v1::Service;
-use kube::{
-    api::{Api, ListParams},
-    {Client, Error},
-};
-use std::sync::mpsc::Sender;
+use tokio::task::JoinHandle;
 
-pub fn check_services(namespace: String, expected_services: Vec<String>, tx: Sender<KubeMessage>) {
-    tokio::spawn(async move {
-        let msg = match Client::try_default().await {
-            Ok(client) => {
-                let services_request: Api<Service> = Api::namespaced(client, namespace.as_str());
-                let service_list = services_request.list(&ListParams::default()).await;
-                let missing_services: Result<Vec<String>, Error> = service_list.map(|list| {
-                    let svc_names: Vec<Option<String>> =
-                        list.iter().map(|s| s.metadata.name.clone()).collect();
-                    missing_services(expected_services, svc_names)
-                });
-                println!("services: {:?}", missing_services);
-
-                match missing_services {
-                    Ok(services) => {
-                        if services.iter().count() > 0 {
-                            success(KubeStatus::Bad(format!(
-                                "Services not in k8s: {}",
-                                services.join(", ")
-                            )))
-                        } else {
-                            success(KubeStatus::Good)
-                        }
-                    }
-                    Err(err) => error(err),
-                }
-            }
-            Err(err) => error(err),
-        };
+/// Watches the services in `namespace`, reporting any expected ones that are missing
+pub fn watch_services(
+    clients: ClientManager,
+    namespace: String,
+    generation: u64,
+    expected_services: Vec<String>,
+    tx: MessageSender,
+) -> JoinHandle<()> {
+    watch_namespaced(
+        clients,
+        namespace,
+        generation,
+        tx,
+        move |services: Vec<Service>| {
+            vec![success(services_status(
+                expected_services.clone(),
+                &services,
+            ))]
+        },
+        error,
+    )
+}
 
-        match tx.send(msg) {
-            Ok(_) => {}
-            Err(e) => println!("Failed sending message about services: {}", e),
-        }
-    });
+pub fn services_status(expected: Vec<String>, services: &[Service]) -> KubeStatus {
+    let svc_names: Vec<Option<String>> = services.iter().map(|s| s.metadata.name.clone()).collect();
+    let missing = missing_services(expected, svc_names);
+    if !missing.is_empty() {
+        KubeStatus::Bad(format!("Services not in k8s: {}", missing.join(", ")))
+    } else {
+        KubeStatus::Good
+    }
 }
 
 fn missing_services(expected: Vec<String>, actual: Vec<Option<String>>) -> Vec<String> {
@@ -50,16 +46,20 @@ fn missing_services(expected: Vec<String>, actual: Vec<Option<String>>) -> Vec<S
         .collect()
 }
 
-fn success(status: KubeStatus) -> KubeMessage {
-    KubeMessage::Resource(Ok(KubeResource {
+pub fn resource(status: KubeStatus) -> KubeResource {
+    KubeResource {
         name: "service".to_owned(),
         display: "Services".to_owned(),
         status,
-    }))
+    }
+}
+
+fn success(status: KubeStatus) -> KubeMessage {
+    KubeMessage::Resource(Ok(resource(status)))
 }
 
 fn error(err: Error) -> KubeMessage {
-    KubeMessage::Resource(Err(err))
+    KubeMessage::Resource(Err(KubeError::new("service", err)))
 }
 
 #[cfg(test)]
@@ -0,0 +1,121 @@
+use super::channel::MessageSender;
+use super::client::ClientManager;
+use super::Error;
+use crate::KubeMessage;
+use futures::StreamExt;
+use k8s_openapi::NamespaceResourceScope;
+use kube::{
+    runtime::{reflector, watcher, WatchStreamExt},
+    Api, Resource,
+};
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+use tokio::task::JoinHandle;
+
+/// Handles to the watches running for one namespace. Dropping this stops them all.
+pub struct Watches {
+    namespace: String,
+    generation: u64,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl Watches {
+    pub fn new(namespace: String, generation: u64, handles: Vec<JoinHandle<()>>) -> Self {
+        Self {
+            namespace,
+            generation,
+            handles,
+        }
+    }
+
+    pub fn namespace(&self) -> &str {
+        self.namespace.as_str()
+    }
+
+    /// What the messages of these watches are tagged with
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+impl Drop for Watches {
+    fn drop(&mut self) {
+        for handle in self.handles.iter() {
+            handle.abort();
+        }
+    }
+}
+
+/// Keeps a local cache of every `K` in the namespace up to date and calls `on_change`
+/// with the whole cache each time it changes, sending the resulting messages to the UI.
+/// Messages are tagged with `generation`, as ones sent before the watch is stopped can
+/// still arrive after it.
+pub fn watch_namespaced<K, F>(
+    clients: ClientManager,
+    namespace: String,
+    generation: u64,
+    tx: MessageSender,
+    on_change: F,
+    on_error: fn(Error) -> KubeMessage,
+) -> JoinHandle<()>
+where
+    K: Resource<Scope = NamespaceResourceScope>
+        + Clone
+        + DeserializeOwned
+        + Debug
+        + Send
+        + Sync
+        + 'static,
+    K::DynamicType: Default + Eq + std::hash::Hash + Clone,
+    F: Fn(Vec<K>) -> Vec<KubeMessage> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let client = match clients.client().await {
+            Ok(client) => client,
+            Err(err) => {
+                let _ = tx.send(watched(generation, on_error(err)));
+                return;
+            }
+        };
+        let api: Api<K> = Api::namespaced(client, namespace.as_str());
+        let (reader, writer) = reflector::store();
+        let stream = reflector(writer, watcher(api, watcher::Config::default())).default_backoff();
+        futures::pin_mut!(stream);
+
+        while let Some(event) = stream.next().await {
+            let messages = match event {
+                Ok(_) => {
+                    let items: Vec<K> = reader.state().iter().map(|k| (**k).clone()).collect();
+                    on_change(items)
+                }
+                Err(err) => match kube_error(err) {
+                    Some(err) => vec![on_error(err.into())],
+                    None => vec![],
+                },
+            };
+            for msg in messages {
+                if let Err(e) = tx.send(watched(generation, msg)) {
+                    println!("Failed sending watch update, stopping watch: {}", e);
+                    return;
+                }
+            }
+        }
+    })
+}
+
+fn watched(generation: u64, msg: KubeMessage) -> KubeMessage {
+    KubeMessage::Watched(generation, Box::new(msg))
+}
+
+fn kube_error(err: watcher::Error) -> Option<kube::Error> {
+    match err {
+        watcher::Error::InitialListFailed(e)
+        | watcher::Error::WatchStartFailed(e)
+        | watcher::Error::WatchFailed(e) => Some(e),
+        watcher::Error::WatchError(resp) => Some(kube::Error::Api(resp)),
+        other => {
+            println!("Watch error: {}", other);
+            None
+        }
+    }
+}
@@ -2,11 +2,17 @@ mod kube_res;
 mod ui;
 
 mod config;
+mod template;
 
 use self::config::Config;
-use self::kube_res::{namespaces::get_namespaces, KubeMessage, KubeResource, KubeStatus};
+use self::kube_res::{
+    channel::channel, client::ClientManager, contexts::get_contexts, namespaces::get_namespaces,
+    ActionOutcome, KubeMessage, KubeResource, KubeStatus,
+};
 
-use self::ui::boards::{status, welcome};
+use self::ui::boards::{clusters, resources, status, welcome};
+use self::ui::notifications::Notifications;
+use self::ui::terminal::Terminals;
 use self::ui::topbar::Topbar;
 
 use eframe::egui;
@@ -20,6 +26,8 @@ use tokio::runtime::Runtime;
 pub enum Board {
     Welcome,
     Status,
+    Clusters,
+    Resources,
 }
 
 fn main() -> Result<(), eframe::Error> {
@@ -60,73 +68,205 @@ struct DevSwitchboard {
     receiver: Receiver<KubeMessage>,
     selected_namespace: String,
     topbar: Topbar,
+    notifications: Notifications,
     namespaces: Vec<String>,
     status_board: status::Board,
+    clusters_board: clusters::Board,
+    resources_board: resources::Board,
     welcome_board: welcome::Board,
+    terminals: Terminals,
     board: Board,
 }
 
 impl DevSwitchboard {
-    fn new(_cc: &CreationContext<'_>, namespaces: Vec<String>, conf: Config) -> Self {
-        let (sender, receiver) = std::sync::mpsc::channel();
-        get_namespaces(sender.clone());
+    fn new(cc: &CreationContext<'_>, namespaces: Vec<String>, conf: Config) -> Self {
+        let (sender, receiver) = channel(cc.egui_ctx.clone());
+        let clients = ClientManager::new(sender.clone());
+        get_contexts(sender.clone());
+        get_namespaces(clients.clone(), sender.clone());
         Self {
             conf,
             receiver,
             selected_namespace: "".to_owned(),
-            topbar: Topbar::new(namespaces.clone(), sender.clone()),
+            topbar: Topbar::new(namespaces.clone(), clients.clone(), sender.clone()),
+            notifications: Notifications::new(),
             namespaces,
-            status_board: status::Board::new(sender.clone()),
-            welcome_board: welcome::Board::new(sender.clone()),
+            status_board: status::Board::new(clients.clone(), sender.clone()),
+            clusters_board: clusters::Board::new(sender.clone()),
+            resources_board: resources::Board::new(clients.clone(), sender.clone()),
+            welcome_board: welcome::Board::new(clients.clone(), sender.clone()),
+            terminals: Terminals::new(clients, sender.clone()),
             board: Board::Welcome,
         }
     }
+
+    fn receive(&mut self, message: KubeMessage) {
+        match message {
+            KubeMessage::Client(res) => {
+                // A rebuilt client means a new cluster or credentials, so the
+                // running watches have to be restarted with it
+                if res.is_ok() {
+                    self.status_board.restart_watches();
+                    self.resources_board.restart();
+                }
+                if let Err(err) = &res {
+                    self.notifications.push("client", err.clone());
+                }
+                self.topbar.receive_client_error(res.err());
+            }
+            KubeMessage::Contexts(res) => match res {
+                Ok(contexts) => self.topbar.receive_contexts(contexts),
+                Err(err) => self.notifications.push("contexts", err),
+            },
+            KubeMessage::Namespaces(res) => match res {
+                Ok(namespaces) => {
+                    self.namespaces = namespaces.clone();
+                    self.topbar.receive_namespaces(namespaces);
+                    self.selected_namespace = "".to_owned();
+                }
+                Err(err) => {
+                    self.notifications.push("namespaces", err.describe());
+                    self.topbar.receive_namespaces_error(err.describe());
+                    // Anything in here would be watched and listed as a namespace
+                    self.selected_namespace.clear();
+                }
+            },
+            KubeMessage::Resource(res) => match res {
+                Ok(new_resource) => {
+                    self.status_board.receive_resource(new_resource);
+                }
+                Err(err) => {
+                    self.status_board.receive_error(&err.name, err.describe());
+                    self.notifications.push(&err.name, err.describe());
+                }
+            },
+            KubeMessage::Watched(generation, message) => {
+                // Anything from watches that have since been replaced is out of date
+                if self.status_board.is_current(generation) {
+                    self.receive(*message);
+                }
+            }
+            KubeMessage::Cluster(id, context, resource) => {
+                self.clusters_board.receive_resource(id, context, resource);
+            }
+            KubeMessage::Pods(pods) => self.status_board.receive_pods(pods),
+            KubeMessage::Logs(id, res) => {
+                let chunk = res.map_err(|err| {
+                    self.notifications.push(&err.name, err.describe());
+                    err.describe()
+                });
+                self.status_board.receive_logs(id, chunk);
+            }
+            KubeMessage::PortForward(forward) => self.welcome_board.receive_port_forward(forward),
+            KubeMessage::Kinds(id, res) => {
+                // Discovery from before the cluster changed says nothing about this one
+                if self.resources_board.is_discovering(id) {
+                    let kinds = res.map_err(|err| {
+                        let description = err.describe();
+                        self.notifications.push("discovery", description.clone());
+                        description
+                    });
+                    self.resources_board.receive_kinds(kinds);
+                }
+            }
+            KubeMessage::Objects(list) => {
+                if let Err(err) = &list.objects {
+                    self.notifications.push(&err.name, err.describe());
+                }
+                self.resources_board.receive_objects(list);
+            }
+            KubeMessage::Object(id, res) => {
+                let object = res.map(|object| *object).map_err(|err| {
+                    self.notifications.push(&err.name, err.describe());
+                    err.describe()
+                });
+                self.status_board.receive_object(id, object.clone());
+                self.resources_board.receive_object(id, object);
+            }
+            KubeMessage::Applied(id, res) => {
+                let applied = res.map_err(|err| {
+                    self.notifications.push(&err.name, err.describe());
+                    err.describe()
+                });
+                self.status_board.receive_applied(id, applied.clone());
+                self.resources_board.receive_applied(id, applied);
+            }
+            KubeMessage::Exec(id, res) => {
+                let event = res.map_err(|err| {
+                    self.notifications.push(&err.name, err.describe());
+                    err.describe()
+                });
+                self.terminals.receive(id, event);
+            }
+            KubeMessage::Action(res) => match res {
+                Ok(action_res) => {
+                    if action_res.outcome == ActionOutcome::Failed {
+                        self.notifications
+                            .push(&action_res.name, action_res.results.clone());
+                    }
+                    self.welcome_board.receive_action_result(action_res);
+                }
+                Err(err) => {
+                    self.notifications.push(&err.name, err.describe());
+                    let description = err.describe();
+                    self.welcome_board
+                        .receive_action_error(err.name, description);
+                }
+            },
+        }
+    }
 }
 
 impl eframe::App for DevSwitchboard {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        match self.receiver.try_recv() {
-            Ok(message) => match message {
-                KubeMessage::Namespaces(res) => match res {
-                    Ok(namespaces) => {
-                        self.namespaces = namespaces.clone();
-                        self.topbar.receive_namespaces(namespaces);
-                        self.selected_namespace = "".to_owned();
-                    }
-                    _ => self.selected_namespace = "Failed - Check login!".to_owned(),
-                },
-                KubeMessage::Resource(res) => match res {
-                    Ok(new_resource) => {
-                        self.status_board.receive_resource(new_resource);
-                    }
-                    _ => {}
-                },
-                KubeMessage::Action(res) => match res {
-                    Ok(action_res) => {
-                        self.welcome_board
-                            .receive_action_result(action_res.name, action_res.results);
-                    }
-                    _ => {}
-                },
-            },
-            _ => {} // don't care if message does not receive
+        // Handle everything that arrived since the last frame, not just one message
+        while let Ok(message) = self.receiver.try_recv() {
+            self.receive(message);
         }
 
         self.status_board.namespace = self.selected_namespace.clone();
         self.welcome_board.namespace = self.selected_namespace.clone();
+        self.welcome_board.context = self.topbar.context();
+        self.clusters_board.namespace = self.selected_namespace.clone();
+        self.resources_board.namespace = self.selected_namespace.clone();
 
         egui::TopBottomPanel::top("header").show(ctx, |ui| {
             self.topbar
                 .display(ui, &mut self.selected_namespace, &mut self.board)
         });
+        egui::TopBottomPanel::bottom("notifications")
+            .show(ctx, |ui| self.notifications.display(ui));
+        if let Some((pod, container)) = self.status_board.shell_request.take() {
+            self.terminals
+                .open(self.selected_namespace.clone(), pod, container);
+        }
+        self.terminals.show(ctx);
         egui::CentralPanel::default().show(ctx, |ui| match self.board {
-            Board::Welcome => self
-                .welcome_board
-                .board(ui, self.conf.links(), self.conf.actions()),
-            Board::Status => {
-                self.status_board
-                    .board(ui, self.conf.kube_services(), self.conf.kube_deployments())
-            }
+            Board::Welcome => self.welcome_board.board(
+                ui,
+                self.conf.links(),
+                self.conf.actions(),
+                self.conf.vars(),
+            ),
+            Board::Status => self.status_board.board(
+                ui,
+                self.conf.kube_services(),
+                self.conf.kube_deployments(),
+                self.conf.thresholds(),
+            ),
+            Board::Clusters => self.clusters_board.board(
+                ui,
+                self.conf.compare_contexts(),
+                self.conf.kube_services(),
+                self.conf.kube_deployments(),
+                self.conf.thresholds(),
+            ),
+            Board::Resources => self.resources_board.board(ui),
         });
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // Stop listening on local ports rather than leaving it to the process exiting
+        self.welcome_board.stop_port_forwards();
+    }
 }
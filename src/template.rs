@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+/// Replaces each `{name}` in `template` with its value from `vars`, in one pass so
+/// values are never filled in themselves. Placeholders without a value are left as
+/// they are.
+pub fn render(template: &str, vars: &HashMap<String, String>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest[1..]
+            .find('}')
+            .and_then(|end| vars.get(&rest[1..end + 1]).map(|value| (end, value)));
+        match value {
+            Some((end, value)) => {
+                rendered.push_str(value);
+                rest = &rest[end + 2..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Names of the `{name}` placeholders in `template`, in the order they appear
+pub fn placeholders(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        match rest.find('}') {
+            Some(end) => {
+                let name = &rest[..end];
+                if !name.is_empty() && !names.iter().any(|n| n == name) {
+                    names.push(name.to_owned());
+                }
+                rest = &rest[end + 1..];
+            }
+            None => break,
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[cfg(test)]
+    mod render {
+        use super::*;
+
+        #[test]
+        pub fn fills_in_every_occurrence() {
+            let vars = vars(&[("namespace", "dev"), ("cluster", "east")]);
+            assert_eq!(
+                render("https://{namespace}.{cluster}.example/{namespace}", &vars),
+                "https://dev.east.example/dev"
+            );
+        }
+
+        #[test]
+        pub fn leaves_unknown_placeholders() {
+            let vars = vars(&[("namespace", "dev")]);
+            assert_eq!(render("{namespace}-{tag}", &vars), "dev-{tag}");
+        }
+
+        #[test]
+        pub fn leaves_braces_in_values_alone() {
+            let vars = vars(&[("a", "{b}"), ("b", "x")]);
+            assert_eq!(render("{a}-{b}", &vars), "{b}-x");
+        }
+
+        #[test]
+        pub fn fills_in_inside_other_braces() {
+            let vars = vars(&[("name", "web")]);
+            assert_eq!(render("f() { echo {name}; }", &vars), "f() { echo web; }");
+        }
+    }
+
+    #[cfg(test)]
+    mod placeholders {
+        use super::*;
+
+        #[test]
+        pub fn lists_each_name_once() {
+            assert_eq!(
+                placeholders("{app}-{namespace}-{app}"),
+                vec!["app".to_owned(), "namespace".to_owned()]
+            );
+        }
+
+        #[test]
+        pub fn ignores_empty_and_unclosed_braces() {
+            assert_eq!(placeholders("{} {open"), Vec::<String>::new());
+        }
+    }
+}
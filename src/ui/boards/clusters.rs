@@ -0,0 +1,141 @@
+use crate::config::Thresholds;
+use crate::kube_res::{
+    channel::MessageSender, client::ClientManager, clusters::check_cluster, logs::next_stream_id,
+};
+use crate::KubeResource;
+use eframe::egui;
+use std::collections::HashMap;
+
+const RESOURCES: [(&str, &str); 3] = [
+    ("service", "Services"),
+    ("deployment", "Deploys"),
+    ("pod", "Pods"),
+];
+
+pub struct Board {
+    pub namespace: String,
+    sender: MessageSender,
+    clients: HashMap<String, ClientManager>,
+    // Keyed by context, then by resource name
+    resources: HashMap<String, HashMap<String, KubeResource>>,
+    checked_namespace: String,
+    // The id of the latest check, as answers from earlier ones can still arrive
+    check_id: u64,
+}
+
+impl Board {
+    pub fn new(sender: MessageSender) -> Board {
+        Board {
+            namespace: "".to_owned(),
+            sender,
+            clients: HashMap::new(),
+            resources: HashMap::new(),
+            checked_namespace: "".to_owned(),
+            check_id: 0,
+        }
+    }
+
+    fn check(
+        &mut self,
+        contexts: Vec<String>,
+        expected_services: Vec<String>,
+        expected_deploys: Vec<String>,
+        thresholds: Thresholds,
+    ) {
+        self.resources = HashMap::new();
+        self.checked_namespace = self.namespace.clone();
+        self.check_id = next_stream_id();
+        for context in contexts {
+            let clients = self
+                .clients
+                .entry(context.clone())
+                .or_insert_with(|| ClientManager::for_context(context.clone()))
+                .clone();
+            let loading = RESOURCES
+                .iter()
+                .map(|(name, display)| {
+                    let resource = KubeResource::new(name.to_string(), display.to_string());
+                    (name.to_string(), resource)
+                })
+                .collect();
+            self.resources.insert(context.clone(), loading);
+            check_cluster(
+                context,
+                clients,
+                self.namespace.clone(),
+                expected_services.clone(),
+                expected_deploys.clone(),
+                thresholds.clone(),
+                self.check_id,
+                self.sender.clone(),
+            );
+        }
+    }
+
+    pub fn board(
+        &mut self,
+        ui: &mut egui::Ui,
+        contexts: Vec<String>,
+        services: Vec<String>,
+        deployments: Vec<String>,
+        thresholds: Thresholds,
+    ) {
+        ui.heading("Status across clusters");
+        if contexts.is_empty() {
+            ui.label("Add kube contexts to `compare_contexts` under [kubernetes] in your config to compare them here");
+            return;
+        }
+        if self.namespace.is_empty() {
+            ui.label("Pick a namespace to compare it across clusters");
+            return;
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Compare").clicked() {
+                self.check(contexts.clone(), services, deployments, thresholds);
+            }
+            if !self.checked_namespace.is_empty() {
+                ui.label(format!("Namespace: {}", self.checked_namespace));
+            }
+        });
+        if self.resources.is_empty() {
+            return;
+        }
+        egui::Grid::new("clusters_grid")
+            .striped(true)
+            .num_columns(contexts.len() + 1)
+            .show(ui, |ui| {
+                ui.strong("Resource");
+                for context in contexts.iter() {
+                    ui.strong(context);
+                }
+                ui.end_row();
+                for (name, display) in RESOURCES.iter() {
+                    ui.label(*display);
+                    for context in contexts.iter() {
+                        match self.resources.get(context).and_then(|r| r.get(*name)) {
+                            Some(resource) if !resource.is_ready() => {
+                                ui.add(egui::widgets::Spinner::new());
+                            }
+                            Some(resource) => {
+                                ui.colored_label(resource.color(), "⬤")
+                                    .on_hover_text(format!("{}", resource.status));
+                            }
+                            None => {
+                                ui.label("-");
+                            }
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+
+    pub fn receive_resource(&mut self, id: u64, context: String, resource: KubeResource) {
+        if id != self.check_id {
+            return;
+        }
+        if let Some(resources) = self.resources.get_mut(&context) {
+            resources.insert(resource.name.clone(), resource);
+        }
+    }
+}
@@ -1,2 +1,4 @@
+pub mod clusters;
+pub mod resources;
 pub mod status;
 pub mod welcome;
@@ -0,0 +1,262 @@
+use crate::kube_res::{
+    browse::{
+        discover_kinds, list_objects, object_status, sort_objects, Applied, ObjectList, SortBy,
+    },
+    channel::MessageSender,
+    client::ClientManager,
+    logs::next_stream_id,
+    pods::format_age,
+};
+use crate::ui::object_view::ObjectView;
+use eframe::egui;
+use kube::api::{ApiResource, DynamicObject};
+
+pub struct Board {
+    pub namespace: String,
+    clients: ClientManager,
+    sender: MessageSender,
+    /// None until discovery has answered
+    kinds: Option<Result<Vec<ApiResource>, String>>,
+    /// The id of the discovery being waited for
+    discovering: Option<u64>,
+    kind: Option<ApiResource>,
+    // The namespace and kind the objects are, or are being, listed for
+    listed: Option<(String, ApiResource)>,
+    /// None while they are being listed. Kept in the picked sort order.
+    objects: Option<Result<Vec<DynamicObject>, String>>,
+    sort: SortBy,
+    descending: bool,
+    /// The object picked in the table
+    detail: Option<ObjectView>,
+}
+
+impl Board {
+    pub fn new(clients: ClientManager, sender: MessageSender) -> Board {
+        Board {
+            namespace: "".to_owned(),
+            clients,
+            sender,
+            kinds: None,
+            discovering: None,
+            kind: None,
+            listed: None,
+            objects: None,
+            sort: SortBy::Name,
+            descending: false,
+            detail: None,
+        }
+    }
+
+    /// Forgets what was discovered, for when the cluster changes
+    pub fn restart(&mut self) {
+        self.kinds = None;
+        self.discovering = None;
+        self.kind = None;
+        self.listed = None;
+        self.objects = None;
+        self.detail = None;
+    }
+
+    fn list(&mut self, kind: ApiResource) {
+        self.listed = Some((self.namespace.clone(), kind.clone()));
+        self.objects = None;
+        list_objects(
+            self.clients.clone(),
+            self.namespace.clone(),
+            kind,
+            self.sender.clone(),
+        );
+    }
+
+    pub fn board(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Browse resources");
+        if self.kinds.is_none() && self.discovering.is_none() {
+            let id = next_stream_id();
+            self.discovering = Some(id);
+            discover_kinds(self.clients.clone(), id, self.sender.clone());
+        }
+        let kinds = match &self.kinds {
+            Some(Ok(kinds)) => kinds.clone(),
+            Some(Err(err)) => {
+                ui.colored_label(egui::Color32::RED, err);
+                if ui.button("Retry").clicked() {
+                    self.kinds = None;
+                }
+                return;
+            }
+            None => {
+                ui.horizontal(|ui| {
+                    ui.add(egui::widgets::Spinner::new());
+                    ui.label("Discovering resource kinds...");
+                });
+                return;
+            }
+        };
+        if self.namespace.is_empty() {
+            ui.label("Pick a namespace to browse its resources");
+            return;
+        }
+        let mut refresh = false;
+        ui.horizontal(|ui| {
+            let selected = self.kind.as_ref().map(kind_label).unwrap_or_default();
+            egui::ComboBox::new("kinds", "Kind")
+                .width(300.0)
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for kind in kinds.iter() {
+                        let label = kind_label(kind);
+                        ui.selectable_value(&mut self.kind, Some(kind.clone()), label);
+                    }
+                });
+            refresh = ui.button("Refresh").clicked();
+        });
+        let kind = match &self.kind {
+            Some(kind) => kind.clone(),
+            None => return,
+        };
+        let wanted = Some((self.namespace.clone(), kind.clone()));
+        if refresh || self.listed != wanted {
+            if self.listed != wanted {
+                self.detail = None;
+            }
+            self.list(kind.clone());
+        }
+
+        if let Some(detail) = self.detail.as_mut() {
+            let mut closed = false;
+            egui::SidePanel::right("resource_detail")
+                .resizable(true)
+                .default_width(ui.available_width() / 2.0)
+                .show_inside(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.strong(&detail.name);
+                        closed = ui.small_button("x").on_hover_text("Close").clicked();
+                    });
+                    detail.contents(ui);
+                });
+            if closed {
+                self.detail = None;
+            }
+        }
+
+        match &self.objects {
+            None => {
+                ui.add(egui::widgets::Spinner::new());
+            }
+            Some(Err(err)) => {
+                ui.colored_label(egui::Color32::RED, err);
+            }
+            Some(Ok(objects)) if objects.is_empty() => {
+                ui.label(format!("No {} in {}", kind.plural, self.namespace));
+            }
+            Some(Ok(_)) => {
+                egui::ScrollArea::vertical()
+                    .id_source("resource_table")
+                    .show(ui, |ui| self.objects_table(ui));
+            }
+        }
+    }
+
+    /// Name, age and status of each object. Clicking a header sorts by it, clicking a
+    /// name shows the object.
+    fn objects_table(&mut self, ui: &mut egui::Ui) {
+        let kind = match &self.listed {
+            Some((_, kind)) => kind.clone(),
+            None => return,
+        };
+        // Taken out while the table borrows the rest of the board, and put back after
+        let mut objects = match self.objects.take() {
+            Some(Ok(objects)) => objects,
+            other => {
+                self.objects = other;
+                return;
+            }
+        };
+        let sorted = (self.sort, self.descending);
+        egui::Grid::new("resources_table")
+            .striped(true)
+            .num_columns(3)
+            .show(ui, |ui| {
+                for (header, by) in [
+                    ("Name", SortBy::Name),
+                    ("Age", SortBy::Age),
+                    ("Status", SortBy::Status),
+                ] {
+                    let arrow = match (self.sort == by, self.descending) {
+                        (false, _) => "",
+                        (true, false) => " ⏶",
+                        (true, true) => " ⏷",
+                    };
+                    let header = egui::RichText::new(format!("{}{}", header, arrow)).strong();
+                    if ui.selectable_label(self.sort == by, header).clicked() {
+                        self.descending = self.sort == by && !self.descending;
+                        self.sort = by;
+                    }
+                }
+                ui.end_row();
+                for object in objects.iter() {
+                    let name = object.metadata.name.clone().unwrap_or_default();
+                    let selected = self.detail.as_ref().map(|d| d.name.as_str()) == Some(&name);
+                    if ui.selectable_label(selected, &name).clicked() {
+                        self.detail = if selected {
+                            None
+                        } else {
+                            Some(ObjectView::open(
+                                kind.clone(),
+                                name,
+                                self.namespace.clone(),
+                                self.clients.clone(),
+                                self.sender.clone(),
+                            ))
+                        };
+                    }
+                    let created = object.metadata.creation_timestamp.as_ref().map(|t| t.0);
+                    ui.label(format_age(created));
+                    ui.label(object_status(object));
+                    ui.end_row();
+                }
+            });
+        if (self.sort, self.descending) != sorted {
+            sort_objects(&mut objects, self.sort, self.descending);
+        }
+        self.objects = Some(Ok(objects));
+    }
+
+    pub fn receive_object(&mut self, id: u64, object: Result<DynamicObject, String>) {
+        if let Some(detail) = self.detail.as_mut() {
+            detail.receive(id, object);
+        }
+    }
+
+    pub fn receive_applied(&mut self, id: u64, applied: Result<Applied, String>) {
+        if let Some(detail) = self.detail.as_mut() {
+            detail.receive_applied(id, applied);
+        }
+    }
+
+    pub fn is_discovering(&self, id: u64) -> bool {
+        self.discovering == Some(id)
+    }
+
+    pub fn receive_kinds(&mut self, kinds: Result<Vec<ApiResource>, String>) {
+        self.discovering = None;
+        self.kinds = Some(kinds);
+    }
+
+    pub fn receive_objects(&mut self, list: ObjectList) {
+        // Only the latest pick matters, anything else arrived too late
+        if self.listed.as_ref() != Some(&(list.namespace, list.kind)) {
+            return;
+        }
+        let mut objects = list.objects.map_err(|err| err.describe());
+        if let Ok(objects) = objects.as_mut() {
+            sort_objects(objects, self.sort, self.descending);
+        }
+        self.objects = Some(objects);
+    }
+}
+
+/// e.g. `Deployment (apps/v1)`
+fn kind_label(kind: &ApiResource) -> String {
+    format!("{} ({})", kind.kind, kind.api_version)
+}
@@ -1,41 +1,203 @@
-use crate::kube_res::{pods::check_pods, services::check_services};
-use crate::{KubeMessage, KubeResource};
+use crate::config::Thresholds;
+use crate::kube_res::{
+    browse::Applied,
+    channel::MessageSender,
+    client::ClientManager,
+    deployments::watch_deployments,
+    logs::{next_stream_id, LogChunk, LogTarget},
+    pods::{watch_pods, PodSummary},
+    services::watch_services,
+    watch::Watches,
+};
+use crate::ui::log_viewer::LogViewer;
+use crate::ui::object_view::ObjectView;
+use crate::{KubeResource, KubeStatus};
 use eframe::egui;
-use std::sync::mpsc::Sender;
+use eframe::egui::Color32;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::{Pod, Service};
+use kube::api::{ApiResource, DynamicObject};
+use std::time::{Duration, Instant};
+
+// How long the namespace has to stay the same before its watches are started, so
+// typing a name does not start watches for every prefix of it
+const WATCH_DELAY: Duration = Duration::from_millis(500);
 
 pub struct Board {
     resources: Vec<KubeResource>,
-    sender: Sender<KubeMessage>,
+    pods: Vec<PodSummary>,
+    watches: Option<Watches>,
+    // A namespace to watch once it has not changed for a while, and since when
+    pending_namespace: Option<(String, Instant)>,
+    log_viewer: Option<LogViewer>,
+    object_view: Option<ObjectView>,
+    clients: ClientManager,
+    sender: MessageSender,
     pub namespace: String,
+    /// A pod and container the user asked for a shell in, for the terminal panel
+    pub shell_request: Option<(String, String)>,
+}
+
+/// What the user asked for from a row of the board
+enum RowRequest {
+    /// Logs of the target, with the containers to pick from
+    Logs(LogTarget, Vec<String>),
+    /// A shell in a container of a pod
+    Shell(String, String),
+    /// The full object of the given kind and name
+    View(ApiResource, String),
 }
 
 impl Board {
-    pub fn new(sender: Sender<KubeMessage>) -> Board {
+    pub fn new(clients: ClientManager, sender: MessageSender) -> Board {
         Board {
             resources: vec![],
+            pods: vec![],
+            watches: None,
+            pending_namespace: None,
+            log_viewer: None,
+            object_view: None,
+            clients,
             sender,
             namespace: "".to_owned(),
+            shell_request: None,
         }
     }
-    fn check(&mut self, expected_services: Vec<String>, expected_deploys: Vec<String>) {
+    /// (Re)starts the watches for the current namespace, replacing any running ones
+    fn watch(
+        &mut self,
+        expected_services: Vec<String>,
+        expected_deploys: Vec<String>,
+        thresholds: Thresholds,
+    ) {
         self.resources = vec![
             KubeResource::new("service".to_owned(), "Services".to_owned()),
             KubeResource::new("deployment".to_owned(), "Deploys".to_owned()),
             KubeResource::new("pod".to_owned(), "Pods".to_owned()),
         ];
-        check_pods(self.namespace.clone(), self.sender.clone());
-        check_services(
-            self.namespace.clone(),
-            expected_services,
-            self.sender.clone(),
-        );
-    }
-    pub fn board(&mut self, ui: &mut egui::Ui, services: Vec<String>, deployments: Vec<String>) {
+        self.pods = vec![];
+        self.pending_namespace = None;
+        // Messages the old watches already sent are dropped by their generation
+        let generation = next_stream_id();
+        let handles = vec![
+            watch_pods(
+                self.clients.clone(),
+                self.namespace.clone(),
+                generation,
+                thresholds,
+                self.sender.clone(),
+            ),
+            watch_services(
+                self.clients.clone(),
+                self.namespace.clone(),
+                generation,
+                expected_services,
+                self.sender.clone(),
+            ),
+            watch_deployments(
+                self.clients.clone(),
+                self.namespace.clone(),
+                generation,
+                expected_deploys,
+                self.sender.clone(),
+            ),
+        ];
+        self.watches = Some(Watches::new(self.namespace.clone(), generation, handles));
+    }
+    pub fn board(
+        &mut self,
+        ui: &mut egui::Ui,
+        services: Vec<String>,
+        deployments: Vec<String>,
+        thresholds: Thresholds,
+    ) {
         ui.heading("Status of various resources");
-        if ui.button("Check Status").clicked() {
-            self.check(services, deployments);
+        if self.namespace.is_empty() {
+            self.watches = None;
+            self.pending_namespace = None;
+            self.resources = vec![];
+            self.pods = vec![];
+            self.log_viewer = None;
+            self.object_view = None;
+            ui.label("Pick a namespace to watch its resources");
+            return;
+        }
+        let (expected_services, expected_deploys) = (services.clone(), deployments.clone());
+        let namespace_changed =
+            self.watches.as_ref().map(|w| w.namespace()) != Some(self.namespace.as_str());
+        if ui.button("Refresh").clicked() {
+            self.watch(services, deployments, thresholds);
+        } else if namespace_changed {
+            match &self.pending_namespace {
+                Some((namespace, since)) if *namespace == self.namespace => {
+                    if since.elapsed() >= WATCH_DELAY {
+                        self.watch(services, deployments, thresholds);
+                    } else {
+                        ui.ctx()
+                            .request_repaint_after(WATCH_DELAY.saturating_sub(since.elapsed()));
+                    }
+                }
+                _ => {
+                    // Nothing of the old namespace is shown while waiting for the new one
+                    self.watches = None;
+                    self.resources = vec![];
+                    self.pods = vec![];
+                    self.pending_namespace = Some((self.namespace.clone(), Instant::now()));
+                    ui.ctx().request_repaint_after(WATCH_DELAY);
+                }
+            }
         }
         for resource in self.resources.clone() {
+            let title = egui::RichText::new(format!("{}", resource)).color(resource.color());
+            let header = egui::CollapsingHeader::new(title).id_source(&resource.name);
+            let problem = resource.status != KubeStatus::Good;
+            let shown = match resource.name.as_str() {
+                "pod" if !self.pods.is_empty() => Some(
+                    header
+                        .default_open(self.pods.iter().any(|p| p.bad))
+                        .show(ui, |ui| self.pods_table(ui)),
+                ),
+                "service" if resource.is_ready() && !expected_services.is_empty() => {
+                    let kind = ApiResource::erase::<Service>(&());
+                    Some(header.default_open(problem).show(ui, |ui| {
+                        expected_table(ui, "services_table", &kind, &expected_services)
+                    }))
+                }
+                "deployment" if resource.is_ready() && !expected_deploys.is_empty() => {
+                    let kind = ApiResource::erase::<Deployment>(&());
+                    Some(header.default_open(problem).show(ui, |ui| {
+                        expected_table(ui, "deployments_table", &kind, &expected_deploys)
+                    }))
+                }
+                _ => None,
+            };
+            if let Some(shown) = shown {
+                match shown.body_returned.flatten() {
+                    Some(RowRequest::Logs(target, containers)) => {
+                        self.log_viewer = Some(LogViewer::new(
+                            target,
+                            containers,
+                            self.namespace.clone(),
+                            self.clients.clone(),
+                            self.sender.clone(),
+                        ));
+                    }
+                    Some(RowRequest::Shell(pod, container)) => {
+                        self.shell_request = Some((pod, container));
+                    }
+                    Some(RowRequest::View(kind, name)) => {
+                        self.object_view = Some(ObjectView::open(
+                            kind,
+                            name,
+                            self.namespace.clone(),
+                            self.clients.clone(),
+                            self.sender.clone(),
+                        ));
+                    }
+                    None => {}
+                }
+                continue;
+            }
             ui.horizontal(|ui| {
                 if !resource.is_ready() {
                     ui.add(egui::widgets::Spinner::new());
@@ -43,6 +205,128 @@ impl Board {
                 ui.colored_label(resource.color(), format!("{}", resource));
             });
         }
+        if let Some(viewer) = self.log_viewer.as_mut() {
+            if !viewer.show(ui.ctx()) {
+                self.log_viewer = None;
+            }
+        }
+        if let Some(view) = self.object_view.as_mut() {
+            if !view.show(ui.ctx()) {
+                self.object_view = None;
+            }
+        }
+    }
+    /// Table of every pod, returning what the user asked for from it
+    fn pods_table(&self, ui: &mut egui::Ui) -> Option<RowRequest> {
+        let mut request = None;
+        egui::Grid::new("pods_table")
+            .striped(true)
+            .num_columns(7)
+            .show(ui, |ui| {
+                for header in ["Name", "Phase", "Ready", "Restarts", "Age", "Node", ""] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+                for pod in self.pods.iter() {
+                    let color = if pod.bad {
+                        Color32::RED
+                    } else if pod.suspicious {
+                        Color32::YELLOW
+                    } else {
+                        ui.visuals().text_color()
+                    };
+                    ui.colored_label(color, &pod.name);
+                    ui.colored_label(color, &pod.phase);
+                    ui.colored_label(color, &pod.ready);
+                    ui.colored_label(color, pod.restarts.to_string());
+                    ui.colored_label(color, pod.age());
+                    ui.colored_label(color, &pod.node);
+                    ui.horizontal(|ui| {
+                        if ui.small_button("Logs").clicked() {
+                            request = Some(RowRequest::Logs(
+                                LogTarget::Pod(pod.name.clone()),
+                                pod.containers.clone(),
+                            ));
+                        }
+                        if let Some(deploy) = &pod.deployment {
+                            let button = ui
+                                .small_button("All pods")
+                                .on_hover_text(format!("Logs of every pod of {}", deploy));
+                            if button.clicked() {
+                                request = Some(RowRequest::Logs(
+                                    LogTarget::Deployment(deploy.clone()),
+                                    pod.containers.clone(),
+                                ));
+                            }
+                        }
+                        if ui.small_button("View").clicked() {
+                            let kind = ApiResource::erase::<Pod>(&());
+                            request = Some(RowRequest::View(kind, pod.name.clone()));
+                        }
+                        match pod.containers.as_slice() {
+                            [] => {}
+                            [container] => {
+                                if ui.small_button("Shell").clicked() {
+                                    request = Some(RowRequest::Shell(
+                                        pod.name.clone(),
+                                        container.clone(),
+                                    ));
+                                }
+                            }
+                            containers => {
+                                ui.menu_button("Shell", |ui| {
+                                    for container in containers {
+                                        if ui.button(container).clicked() {
+                                            request = Some(RowRequest::Shell(
+                                                pod.name.clone(),
+                                                container.clone(),
+                                            ));
+                                            ui.close_menu();
+                                        }
+                                    }
+                                });
+                            }
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+        request
+    }
+    /// Marks the resource a check failed for, leaving the others as they are
+    pub fn receive_error(&mut self, resource_name: &str, error: String) {
+        for r in self.resources.iter_mut() {
+            if r.name == resource_name {
+                r.status = KubeStatus::Error(error.clone());
+            }
+        }
+    }
+    pub fn receive_logs(&mut self, id: u64, chunk: Result<LogChunk, String>) {
+        if let Some(viewer) = self.log_viewer.as_mut() {
+            viewer.receive_logs(id, chunk);
+        }
+    }
+    pub fn receive_object(&mut self, id: u64, object: Result<DynamicObject, String>) {
+        if let Some(view) = self.object_view.as_mut() {
+            view.receive(id, object);
+        }
+    }
+
+    pub fn receive_applied(&mut self, id: u64, applied: Result<Applied, String>) {
+        if let Some(view) = self.object_view.as_mut() {
+            view.receive_applied(id, applied);
+        }
+    }
+    /// Stops the running watches so they are started again on the next frame
+    pub fn restart_watches(&mut self) {
+        self.watches = None;
+    }
+    /// Whether messages tagged with `generation` come from the running watches
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.watches.as_ref().map(|w| w.generation()) == Some(generation)
+    }
+    pub fn receive_pods(&mut self, pods: Vec<PodSummary>) {
+        self.pods = pods;
     }
     pub fn receive_resource(&mut self, resource: KubeResource) {
         self.resources = self
@@ -58,3 +342,26 @@ impl Board {
             .collect();
     }
 }
+
+/// The expected objects of one kind, each with a button to view it
+fn expected_table(
+    ui: &mut egui::Ui,
+    id: &str,
+    kind: &ApiResource,
+    names: &[String],
+) -> Option<RowRequest> {
+    let mut request = None;
+    egui::Grid::new(id)
+        .striped(true)
+        .num_columns(2)
+        .show(ui, |ui| {
+            for name in names {
+                ui.label(name);
+                if ui.small_button("View").clicked() {
+                    request = Some(RowRequest::View(kind.clone(), name.clone()));
+                }
+                ui.end_row();
+            }
+        });
+    request
+}
@@ -1,9 +1,15 @@
-use crate::kube_res::actions::run_action;
-use crate::KubeMessage;
+use crate::kube_res::{
+    actions::run_action, channel::MessageSender, client::ClientManager, exec::CommandOutput,
+    port_forward::PortForward, secrets::SecretValue, ActionOutcome, ActionResult,
+};
+use crate::template::{placeholders, render};
 use eframe::egui;
 use serde::Deserialize;
 use std::collections::HashMap;
-use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+// Revealed secret values are masked again after this long
+const REVEAL_FOR: Duration = Duration::from_secs(30);
 
 #[derive(Deserialize, Clone)]
 pub struct Link {
@@ -16,11 +22,34 @@ pub struct Action {
     pub name: String,
     pub resource: String,
     pub action: String,
+    /// For `scale-deployment`. When not set the user is asked for it.
+    #[serde(default)]
+    pub replicas: Option<i32>,
+    /// For `port-forward`, the port on the pod or service
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// For `port-forward`, the port to listen on. Defaults to `port`, 0 picks a free one.
+    #[serde(default)]
+    pub local_port: Option<u16>,
+    /// For `exec`, the command and its arguments
+    #[serde(default)]
+    pub command: Vec<String>,
+    /// For `exec`, the container to run in when the pod has more than one
+    #[serde(default)]
+    pub container: Option<String>,
+    /// Values the user fills in before running, used as `{name}` in `resource` and
+    /// `command`
+    #[serde(default)]
+    pub params: Vec<String>,
+    /// What the user confirmed the action should be run on
+    #[serde(skip)]
+    pub confirmed: Vec<String>,
 }
 
 enum ActionStatus {
     Available,
     Running,
+    Confirming,
     Success,
     Failed,
 }
@@ -28,81 +57,381 @@ enum ActionStatus {
 struct ActionState {
     status: ActionStatus,
     result: String,
+    secrets: Vec<SecretValue>,
+    output: Option<CommandOutput>,
+    // The namespace and pods listed for the user to confirm
+    pods: Option<(String, Vec<String>)>,
+    // When each revealed secret key was revealed
+    revealed: HashMap<String, Instant>,
+}
+
+impl ActionState {
+    fn new(status: ActionStatus, result: String) -> ActionState {
+        ActionState {
+            status,
+            result,
+            secrets: vec![],
+            output: None,
+            pods: None,
+            revealed: HashMap::new(),
+        }
+    }
 }
 
 pub struct Board {
     pub namespace: String,
-    sender: Sender<KubeMessage>,
+    /// The kube context and its cluster
+    pub context: (String, String),
+    clients: ClientManager,
+    sender: MessageSender,
     action_results: HashMap<String, ActionState>,
+    // What the user typed for actions that ask for input, keyed by action and input name
+    inputs: HashMap<(String, String), String>,
+    forwards: Vec<PortForward>,
 }
 
 impl Board {
-    pub fn new(sender: Sender<KubeMessage>) -> Board {
+    pub fn new(clients: ClientManager, sender: MessageSender) -> Board {
         Board {
             namespace: "".to_owned(),
+            context: ("".to_owned(), "".to_owned()),
+            clients,
             sender,
             action_results: HashMap::new(),
+            inputs: HashMap::new(),
+            forwards: vec![],
         }
     }
-    pub fn board(&mut self, ui: &mut egui::Ui, links: Vec<Link>, actions: Vec<Action>) {
+    pub fn board(
+        &mut self,
+        ui: &mut egui::Ui,
+        links: Vec<Link>,
+        actions: Vec<Action>,
+        vars: HashMap<String, String>,
+    ) {
+        let vars = self.template_vars(vars);
         if links.len() < 1 && actions.len() < 1 {
             ui.label("Welcome to the Dev Switchboard! Pick a board from the buttons above");
         } else {
             ui.heading("Links");
             links.iter().for_each(|link| {
                 let label = format!("{}", link.name);
-                let url = link.url.replace("{namespace}", self.namespace.as_str());
+                let url = render(&link.url, &vars);
                 ui.hyperlink_to(label, url.clone());
             });
             ui.separator();
             ui.heading("Actions");
             actions.iter().for_each(|action| {
                 let label = format!("{}", action.name);
-                if ui.button(label).clicked() {
-                    println!(
-                        "Taking an action! {}, for res: {}",
-                        action.action, action.resource
-                    );
-                    run_action(self.namespace.clone(), action.clone(), self.sender.clone());
-                    self.action_results.insert(
-                        action.name.clone(),
-                        ActionState {
-                            status: ActionStatus::Running,
-                            result: "".to_owned(),
-                        },
-                    );
+                let clicked = ui
+                    .horizontal(|ui| {
+                        for input in Self::input_names(action) {
+                            let width = if input == "replicas" { 40.0 } else { 100.0 };
+                            let key = (action.name.clone(), input.clone());
+                            ui.add(
+                                egui::TextEdit::singleline(self.inputs.entry(key).or_default())
+                                    .desired_width(width)
+                                    .hint_text(input),
+                            );
+                        }
+                        ui.button(label).clicked()
+                    })
+                    .inner;
+                if clicked {
+                    self.start_action(action.clone(), self.namespace.clone(), &vars);
                 }
+                let mut confirmed = None;
+                let mut show_secrets = false;
                 let res = self.action_results.get(&action.name);
                 match res {
                     Some(r) => match r.status {
+                        ActionStatus::Success | ActionStatus::Failed if r.output.is_some() => {
+                            command_output(ui, r);
+                        }
+                        ActionStatus::Success if !r.secrets.is_empty() => show_secrets = true,
                         ActionStatus::Success => {
                             egui::TextEdit::multiline(&mut r.result.clone().as_str())
                                 .font(egui::TextStyle::Monospace)
                                 .show(ui);
                         }
                         ActionStatus::Running => {
-                            ui.add(egui::widgets::Spinner::new());
+                            ui.horizontal(|ui| {
+                                ui.add(egui::widgets::Spinner::new());
+                                ui.label(&r.result);
+                            });
+                        }
+                        ActionStatus::Confirming => {
+                            let (namespace, pods) = r.pods.clone().unwrap_or_default();
+                            ui.label(format!("{} will run in {} on:", action.name, namespace));
+                            for pod in pods.iter() {
+                                ui.monospace(pod);
+                            }
+                            ui.horizontal(|ui| {
+                                if ui.button("Confirm").clicked() {
+                                    confirmed = Some(true);
+                                }
+                                if ui.button("Cancel").clicked() {
+                                    confirmed = Some(false);
+                                }
+                            });
                         }
                         _ => {
-                            ui.label(format!(
-                                "Action {} failed! Result: {}",
-                                action.name, r.result
-                            ));
+                            ui.colored_label(
+                                egui::Color32::RED,
+                                format!("Action {} failed! Result: {}", action.name, r.result),
+                            );
                         }
                     },
                     _ => {}
                 }
+                if show_secrets {
+                    self.secrets_table(ui, &action.name);
+                }
+                match confirmed {
+                    Some(true) => {
+                        let mut action = action.clone();
+                        let listed = self.action_results[&action.name].pods.clone();
+                        if let Some((namespace, pods)) = listed {
+                            action.confirmed = pods;
+                            self.start_action(action, namespace, &vars);
+                        }
+                    }
+                    Some(false) => {
+                        self.action_results.remove(&action.name);
+                    }
+                    None => {}
+                }
+            });
+            if !self.forwards.is_empty() {
+                self.port_forwards(ui);
+            }
+        }
+    }
+
+    /// Running port forwards, each with a button to stop it
+    fn port_forwards(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Port forwards");
+        let mut stopped = None;
+        egui::Grid::new("port_forwards")
+            .striped(true)
+            .num_columns(5)
+            .show(ui, |ui| {
+                for (i, forward) in self.forwards.iter().enumerate() {
+                    ui.label(&forward.name);
+                    ui.hyperlink_to(
+                        forward.local_addr.to_string(),
+                        format!("http://{}", forward.local_addr),
+                    );
+                    ui.label(format!("{}:{}", forward.target.pod, forward.target.port));
+                    ui.label(format_bytes(forward.bytes()));
+                    ui.horizontal(|ui| {
+                        if ui.small_button("Stop").clicked() {
+                            stopped = Some(i);
+                        }
+                        if let Some(err) = forward.last_error() {
+                            ui.colored_label(egui::Color32::RED, err);
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+        if let Some(i) = stopped {
+            // Dropping the forward stops it
+            self.forwards.remove(i);
+        }
+        // Keep the byte counts moving while nothing else causes a repaint
+        ui.ctx().request_repaint_after(Duration::from_secs(1));
+    }
+
+    /// Secret values, masked unless revealed, each with a button to copy it
+    fn secrets_table(&mut self, ui: &mut egui::Ui, action_name: &str) {
+        let state = match self.action_results.get_mut(action_name) {
+            Some(state) => state,
+            None => return,
+        };
+        state.revealed.retain(|_, at| at.elapsed() < REVEAL_FOR);
+        egui::Grid::new(format!("secrets_{}", action_name))
+            .striped(true)
+            .num_columns(3)
+            .show(ui, |ui| {
+                for secret in state.secrets.iter() {
+                    let key = &secret.key;
+                    ui.monospace(key);
+                    let revealed = state.revealed.contains_key(key);
+                    if revealed {
+                        ui.monospace(&secret.display);
+                    } else {
+                        ui.monospace("••••••••");
+                    }
+                    ui.horizontal(|ui| {
+                        let toggle = if revealed { "Hide" } else { "Reveal" };
+                        if ui.small_button(toggle).clicked() {
+                            if revealed {
+                                state.revealed.remove(key);
+                            } else {
+                                state.revealed.insert(key.clone(), Instant::now());
+                            }
+                        }
+                        // The value as stored, not the summary shown for certificates
+                        // and binary data, which can only be copied as base64
+                        let copy = if secret.binary { "Copy base64" } else { "Copy" };
+                        if ui.small_button(copy).clicked() {
+                            ui.output_mut(|o| o.copied_text = secret.raw.clone());
+                        }
+                    });
+                    ui.end_row();
+                }
             });
+        // Wake up to hide the next value due to be masked, even if nothing else happens
+        if let Some(first) = state.revealed.values().min() {
+            ui.ctx()
+                .request_repaint_after(REVEAL_FOR.saturating_sub(first.elapsed()));
         }
     }
 
-    pub fn receive_action_result(&mut self, action_name: String, result: String) {
+    /// Values for placeholders: the `vars` from config, then what is picked in the UI
+    fn template_vars(&self, mut vars: HashMap<String, String>) -> HashMap<String, String> {
+        vars.insert("namespace".to_owned(), self.namespace.clone());
+        vars.insert("context".to_owned(), self.context.0.clone());
+        vars.insert("cluster".to_owned(), self.context.1.clone());
+        vars
+    }
+
+    /// What the user has to fill in before running `action`
+    fn input_names(action: &Action) -> Vec<String> {
+        let mut names = action.params.clone();
+        if action.action == "scale-deployment" && action.replicas.is_none() {
+            names.push("replicas".to_owned());
+        }
+        names
+    }
+
+    fn input(&self, action: &Action, name: &str) -> String {
+        let key = (action.name.clone(), name.to_owned());
+        self.inputs.get(&key).cloned().unwrap_or_default()
+    }
+
+    /// Runs `action` in `namespace`, once it has every value it needs
+    fn start_action(
+        &mut self,
+        mut action: Action,
+        namespace: String,
+        vars: &HashMap<String, String>,
+    ) {
+        let mut vars = vars.clone();
+        for param in action.params.iter() {
+            vars.insert(param.clone(), self.input(&action, param).trim().to_owned());
+        }
+        let empty: Vec<String> = action
+            .params
+            .iter()
+            .filter(|p| vars[*p].is_empty())
+            .cloned()
+            .collect();
+        if !empty.is_empty() {
+            let error = format!("Fill in {} first", empty.join(", "));
+            self.receive_action_error(action.name, error);
+            return;
+        }
+        action.resource = render(&action.resource, &vars);
+        // Commands often have braces of their own, so unknown names are left alone
+        action.command = action.command.iter().map(|a| render(a, &vars)).collect();
+        let unknown = placeholders(&action.resource);
+        if !unknown.is_empty() {
+            let error = format!("No value for {}", unknown.join(", "));
+            self.receive_action_error(action.name, error);
+            return;
+        }
+        if action.action == "scale-deployment" && action.replicas.is_none() {
+            let input = self.input(&action, "replicas");
+            match input.trim().parse::<i32>() {
+                Ok(replicas) if replicas >= 0 => action.replicas = Some(replicas),
+                _ => {
+                    let error = format!("\"{}\" is not a number of replicas", input);
+                    self.receive_action_error(action.name, error);
+                    return;
+                }
+            }
+        }
+        println!(
+            "Taking an action! {}, for res: {}",
+            action.action, action.resource
+        );
         self.action_results.insert(
-            action_name,
-            ActionState {
-                status: ActionStatus::Success,
-                result,
-            },
+            action.name.clone(),
+            ActionState::new(ActionStatus::Running, "".to_owned()),
         );
+        run_action(self.clients.clone(), namespace, action, self.sender.clone());
+    }
+
+    pub fn receive_port_forward(&mut self, forward: PortForward) {
+        self.forwards.push(forward);
+    }
+
+    pub fn stop_port_forwards(&mut self) {
+        self.forwards.clear();
+    }
+
+    pub fn receive_action_error(&mut self, action_name: String, error: String) {
+        self.action_results
+            .insert(action_name, ActionState::new(ActionStatus::Failed, error));
+    }
+
+    pub fn receive_action_result(&mut self, result: ActionResult) {
+        let status = match result.outcome {
+            ActionOutcome::InProgress => ActionStatus::Running,
+            ActionOutcome::NeedsConfirmation => ActionStatus::Confirming,
+            ActionOutcome::Done => ActionStatus::Success,
+            ActionOutcome::Failed => ActionStatus::Failed,
+        };
+        let mut state = ActionState::new(status, result.results);
+        state.secrets = result.secrets;
+        state.output = result.output;
+        state.pods = result.pods;
+        self.action_results.insert(result.name, state);
+    }
+}
+
+/// How an `exec` action's command exited, then what it printed to stdout and stderr
+fn command_output(ui: &mut egui::Ui, state: &ActionState) {
+    let output = match &state.output {
+        Some(output) => output,
+        None => return,
+    };
+    let color = match output.exit_code {
+        Some(0) => egui::Color32::GREEN,
+        _ => egui::Color32::RED,
+    };
+    let status = match output.exit_code {
+        Some(code) => format!("Exit status {}", code),
+        None => "Exit status unknown".to_owned(),
+    };
+    ui.horizontal(|ui| {
+        ui.colored_label(color, status);
+        ui.label(&state.result);
+    });
+    for (stream, text) in [("stdout", &output.stdout), ("stderr", &output.stderr)] {
+        if text.is_empty() {
+            continue;
+        }
+        ui.horizontal(|ui| {
+            ui.strong(stream);
+            if ui.small_button("Copy").clicked() {
+                ui.output_mut(|o| o.copied_text = text.clone());
+            }
+        });
+        egui::TextEdit::multiline(&mut text.as_str())
+            .font(egui::TextStyle::Monospace)
+            .desired_width(f32::INFINITY)
+            .show(ui);
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 20 => format!("{:.1} MiB", b as f64 / (1 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1} KiB", b as f64 / (1 << 10) as f64),
+        b => format!("{} B", b),
     }
 }
@@ -0,0 +1,295 @@
+use crate::kube_res::{
+    channel::MessageSender,
+    client::ClientManager,
+    logs::{
+        merge_lines, next_stream_id, parse_duration, stream_logs, LogChunk, LogLine, LogOptions,
+        LogTarget,
+    },
+};
+use eframe::egui;
+use eframe::egui::Color32;
+use tokio::task::JoinHandle;
+
+// Older lines are dropped past this, so following a chatty pod does not eat memory
+const MAX_LINES: usize = 20_000;
+
+// Prefix colours for the pods of a deployment, in the order they first log
+const POD_COLORS: [Color32; 6] = [
+    Color32::LIGHT_BLUE,
+    Color32::LIGHT_GREEN,
+    Color32::GOLD,
+    Color32::LIGHT_RED,
+    Color32::KHAKI,
+    Color32::from_rgb(200, 150, 255),
+];
+
+/// A window showing the logs of a pod, or of every pod of a deployment interleaved
+/// by time, with the kubectl options that matter most
+pub struct LogViewer {
+    target: LogTarget,
+    containers: Vec<String>,
+    container: String,
+    follow: bool,
+    tail: String,
+    since: String,
+    previous: bool,
+    paused: bool,
+    search: String,
+    timestamps: bool,
+    // Pods seen so far, which also decides their colour
+    pods: Vec<String>,
+    pod_filter: Option<String>,
+    lines: Vec<LogLine>,
+    // Lines received while paused, shown once the viewer is resumed
+    pending: Vec<LogLine>,
+    errors: Vec<String>,
+    streaming: bool,
+    stream_id: u64,
+    stream: Option<JoinHandle<()>>,
+    clients: ClientManager,
+    namespace: String,
+    sender: MessageSender,
+}
+
+impl LogViewer {
+    pub fn new(
+        target: LogTarget,
+        containers: Vec<String>,
+        namespace: String,
+        clients: ClientManager,
+        sender: MessageSender,
+    ) -> LogViewer {
+        let mut viewer = LogViewer {
+            target,
+            container: containers.first().cloned().unwrap_or_default(),
+            containers,
+            follow: true,
+            tail: "500".to_owned(),
+            since: "".to_owned(),
+            previous: false,
+            paused: false,
+            search: "".to_owned(),
+            timestamps: false,
+            pods: vec![],
+            pod_filter: None,
+            lines: vec![],
+            pending: vec![],
+            errors: vec![],
+            streaming: false,
+            stream_id: 0,
+            stream: None,
+            clients,
+            namespace,
+            sender,
+        };
+        viewer.start();
+        viewer
+    }
+
+    /// (Re)starts the stream with the current options, clearing what was shown
+    fn start(&mut self) {
+        self.stop();
+        self.lines.clear();
+        self.pending.clear();
+        self.errors.clear();
+        self.streaming = true;
+        self.stream_id = next_stream_id();
+        let options = LogOptions {
+            container: Some(self.container.clone()).filter(|c| !c.is_empty()),
+            follow: self.follow,
+            tail_lines: self.tail.trim().parse().ok(),
+            since_seconds: parse_duration(&self.since),
+            previous: self.previous,
+        };
+        self.stream = Some(stream_logs(
+            self.clients.clone(),
+            self.namespace.clone(),
+            self.target.clone(),
+            options,
+            self.stream_id,
+            self.sender.clone(),
+        ));
+    }
+
+    fn stop(&mut self) {
+        if let Some(stream) = self.stream.take() {
+            stream.abort();
+        }
+        self.streaming = false;
+    }
+
+    pub fn receive_logs(&mut self, id: u64, chunk: Result<LogChunk, String>) {
+        if id != self.stream_id {
+            return;
+        }
+        match chunk {
+            Ok(chunk) => {
+                for line in chunk.lines.iter() {
+                    if !self.pods.contains(&line.pod) {
+                        self.pods.push(line.pod.clone());
+                    }
+                }
+                if self.paused {
+                    self.pending.extend(chunk.lines);
+                    drop_oldest(&mut self.pending);
+                } else {
+                    merge_lines(&mut self.lines, chunk.lines);
+                }
+                if chunk.done {
+                    self.streaming = false;
+                }
+            }
+            // With several pods one failing does not stop the others, so the stream
+            // only ends on `done`
+            Err(err) => self.errors.push(err),
+        }
+        drop_oldest(&mut self.lines);
+    }
+
+    /// Shows the viewer window, returning false once the user has closed it
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        let mut open = true;
+        let title = match &self.target {
+            LogTarget::Pod(pod) => format!("Logs: {}", pod),
+            LogTarget::Deployment(deploy) => format!("Logs: all pods of {}", deploy),
+        };
+        egui::Window::new(title)
+            .id(egui::Id::new("log_viewer"))
+            .open(&mut open)
+            .default_size([800.0, 500.0])
+            .resizable(true)
+            .show(ctx, |ui| self.contents(ui));
+        if !open {
+            self.stop();
+        }
+        open
+    }
+
+    fn contents(&mut self, ui: &mut egui::Ui) {
+        let mut restart = false;
+        ui.horizontal(|ui| {
+            if self.containers.len() > 1 {
+                ui.label("Container:");
+                let before = self.container.clone();
+                egui::ComboBox::new("log_container", "")
+                    .selected_text(self.container.clone())
+                    .show_ui(ui, |ui| {
+                        for c in self.containers.iter() {
+                            ui.selectable_value(&mut self.container, c.clone(), c.clone());
+                        }
+                    });
+                restart |= before != self.container;
+            }
+            restart |= ui.checkbox(&mut self.follow, "Follow").changed();
+            restart |= ui
+                .checkbox(&mut self.previous, "Previous container")
+                .changed();
+            ui.label("Tail:");
+            restart |= ui
+                .add(egui::TextEdit::singleline(&mut self.tail).desired_width(50.0))
+                .lost_focus();
+            ui.label("Since:");
+            restart |= ui
+                .add(
+                    egui::TextEdit::singleline(&mut self.since)
+                        .desired_width(50.0)
+                        .hint_text("10m"),
+                )
+                .lost_focus();
+            restart |= ui.button("Reload").clicked();
+        });
+        if restart {
+            self.start();
+        }
+        ui.horizontal(|ui| {
+            let pause_label = if self.paused { "Resume" } else { "Pause" };
+            if ui.button(pause_label).clicked() {
+                self.paused = !self.paused;
+                if !self.paused {
+                    let pending = std::mem::take(&mut self.pending);
+                    merge_lines(&mut self.lines, pending);
+                    drop_oldest(&mut self.lines);
+                }
+            }
+            ui.checkbox(&mut self.timestamps, "Timestamps");
+            if self.aggregated() {
+                ui.label("Pod:");
+                egui::ComboBox::new("log_pod_filter", "")
+                    .selected_text(self.pod_filter.clone().unwrap_or("All".to_owned()))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.pod_filter, None, "All");
+                        for pod in self.pods.iter() {
+                            ui.selectable_value(&mut self.pod_filter, Some(pod.clone()), pod);
+                        }
+                    });
+            }
+            ui.label("Search:");
+            ui.text_edit_singleline(&mut self.search);
+            if self.streaming {
+                ui.add(egui::widgets::Spinner::new());
+            }
+            if self.paused && !self.pending.is_empty() {
+                ui.label(format!("{} new lines", self.pending.len()));
+            }
+        });
+        for err in self.errors.iter() {
+            ui.colored_label(Color32::RED, err);
+        }
+        ui.separator();
+
+        let search = self.search.to_lowercase();
+        let shown: Vec<&LogLine> = self
+            .lines
+            .iter()
+            .filter(|line| self.pod_filter.as_ref().is_none_or(|pod| &line.pod == pod))
+            .filter(|line| search.is_empty() || line.text.to_lowercase().contains(&search))
+            .collect();
+        if !search.is_empty() {
+            ui.label(format!("{} matching lines", shown.len()));
+        }
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::both()
+            .auto_shrink([false, false])
+            .stick_to_bottom(!self.paused)
+            .show_rows(ui, row_height, shown.len(), |ui, rows| {
+                for line in shown[rows].iter() {
+                    ui.horizontal(|ui| {
+                        ui.spacing_mut().item_spacing.x = 0.0;
+                        if self.aggregated() {
+                            let prefix = egui::RichText::new(format!("[{}] ", line.pod))
+                                .monospace()
+                                .color(self.pod_color(&line.pod));
+                            ui.label(prefix);
+                        }
+                        if let (true, Some(time)) = (self.timestamps, line.time) {
+                            ui.monospace(format!("{} ", time.format("%Y-%m-%d %H:%M:%S%.3f")));
+                        }
+                        ui.monospace(line.text.as_str());
+                    });
+                }
+            });
+    }
+
+    fn aggregated(&self) -> bool {
+        matches!(self.target, LogTarget::Deployment(_))
+    }
+
+    fn pod_color(&self, pod: &str) -> Color32 {
+        let index = self.pods.iter().position(|p| p == pod).unwrap_or(0);
+        POD_COLORS[index % POD_COLORS.len()]
+    }
+}
+
+impl Drop for LogViewer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// Keeps only the newest `MAX_LINES`, both shown and waiting while paused
+fn drop_oldest(lines: &mut Vec<LogLine>) {
+    if lines.len() > MAX_LINES {
+        let extra = lines.len() - MAX_LINES;
+        lines.drain(..extra);
+    }
+}
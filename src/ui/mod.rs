@@ -1,2 +1,6 @@
 pub mod boards;
+pub mod log_viewer;
+pub mod notifications;
+pub mod object_view;
+pub mod terminal;
 pub mod topbar;
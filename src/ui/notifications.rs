@@ -0,0 +1,123 @@
+use eframe::egui;
+use eframe::egui::Color32;
+use k8s_openapi::chrono::{DateTime, Local};
+
+// Older errors are dropped past this many
+const MAX_NOTIFICATIONS: usize = 200;
+
+struct Notification {
+    time: DateTime<Local>,
+    source: String,
+    message: String,
+}
+
+/// Every error reported while the app is running, kept until the user clears them
+pub struct Notifications {
+    notifications: Vec<Notification>,
+}
+
+impl Notifications {
+    pub fn new() -> Notifications {
+        Notifications {
+            notifications: vec![],
+        }
+    }
+
+    /// Adds an error unless it repeats the last one from the same source, as watches
+    /// keep reporting a failure for as long as it lasts
+    pub fn push(&mut self, source: &str, message: String) {
+        let last = self.notifications.iter().rev().find(|n| n.source == source);
+        if last.is_some_and(|n| n.message == message) {
+            return;
+        }
+        if self.notifications.len() >= MAX_NOTIFICATIONS {
+            self.notifications.remove(0);
+        }
+        self.notifications.push(Notification {
+            time: Local::now(),
+            source: source.to_owned(),
+            message,
+        });
+    }
+
+    pub fn display(&mut self, ui: &mut egui::Ui) {
+        let title = format!("Errors ({})", self.notifications.len());
+        egui::CollapsingHeader::new(title)
+            .id_source("notifications")
+            .show(ui, |ui| {
+                if self.notifications.is_empty() {
+                    ui.label("Nothing has gone wrong yet");
+                    return;
+                }
+                if ui.button("Clear").clicked() {
+                    self.notifications.clear();
+                }
+                egui::ScrollArea::vertical()
+                    .max_height(150.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for n in self.notifications.iter() {
+                            ui.horizontal_wrapped(|ui| {
+                                ui.monospace(n.time.format("%H:%M:%S").to_string());
+                                ui.strong(&n.source);
+                                ui.colored_label(Color32::LIGHT_RED, &n.message);
+                            });
+                        }
+                    });
+            });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn messages(notifications: &Notifications) -> Vec<&str> {
+        notifications
+            .notifications
+            .iter()
+            .map(|n| n.message.as_str())
+            .collect()
+    }
+
+    #[cfg(test)]
+    mod push {
+        use super::*;
+
+        #[test]
+        pub fn skips_repeats_from_the_same_source() {
+            let mut notifications = Notifications::new();
+            notifications.push("pod", "Forbidden".to_owned());
+            notifications.push("service", "Timed out".to_owned());
+            notifications.push("pod", "Forbidden".to_owned());
+            notifications.push("service", "Forbidden".to_owned());
+            assert_eq!(
+                messages(&notifications),
+                vec!["Forbidden", "Timed out", "Forbidden"]
+            );
+        }
+
+        #[test]
+        pub fn keeps_a_repeat_after_a_different_message() {
+            let mut notifications = Notifications::new();
+            notifications.push("pod", "Forbidden".to_owned());
+            notifications.push("pod", "Timed out".to_owned());
+            notifications.push("pod", "Forbidden".to_owned());
+            assert_eq!(
+                messages(&notifications),
+                vec!["Forbidden", "Timed out", "Forbidden"]
+            );
+        }
+
+        #[test]
+        pub fn drops_the_oldest_past_the_cap() {
+            let mut notifications = Notifications::new();
+            for i in 0..MAX_NOTIFICATIONS + 5 {
+                notifications.push("pod", i.to_string());
+            }
+            let messages = messages(&notifications);
+            assert_eq!(messages.len(), MAX_NOTIFICATIONS);
+            assert_eq!(messages[0], "5");
+        }
+    }
+}
@@ -0,0 +1,823 @@
+use crate::kube_res::{
+    browse::{apply_object, get_object, Applied},
+    channel::MessageSender,
+    client::ClientManager,
+    logs::next_stream_id,
+};
+use eframe::egui;
+use eframe::egui::text::LayoutJob;
+use eframe::egui::{Color32, FontId, TextFormat};
+use kube::api::{ApiResource, DynamicObject};
+use serde_json::{Map, Value};
+use similar::{ChangeTag, TextDiff};
+
+const KEY_COLOR: Color32 = Color32::from_rgb(97, 175, 239);
+const STRING_COLOR: Color32 = Color32::from_rgb(152, 195, 121);
+const NUMBER_COLOR: Color32 = Color32::from_rgb(209, 154, 102);
+const KEYWORD_COLOR: Color32 = Color32::from_rgb(198, 120, 221);
+// Unchanged lines shown around each change in a diff
+const DIFF_CONTEXT: usize = 3;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum Format {
+    Yaml,
+    Json,
+}
+
+/// The full object behind a row, fetched fresh, as a tree of collapsible sections
+pub struct ObjectView {
+    id: u64,
+    pub kind: ApiResource,
+    pub name: String,
+    namespace: String,
+    clients: ClientManager,
+    sender: MessageSender,
+    /// None while it is being fetched
+    object: Option<Result<Value, String>>,
+    format: Format,
+    hide_managed_fields: bool,
+    edit: Option<Edit>,
+}
+
+/// Changes being made to the object, on their way to being applied
+struct Edit {
+    text: String,
+    /// The object the text started out as
+    base: Value,
+    /// The fields the text changes, as last checked, to apply once the diff is reviewed
+    patch: Value,
+    /// Whether to take over fields other managers own
+    force: bool,
+    stage: Stage,
+    error: Option<String>,
+}
+
+enum Stage {
+    Editing,
+    /// Waiting for the dry run with this id
+    Checking(u64),
+    /// The dry run came back, changing the live object like this
+    Reviewing(Vec<DiffLine>),
+    /// Other managers own fields the edit changes, as the server explains
+    Conflict(String),
+    Applying(u64),
+}
+
+#[derive(PartialEq, Debug)]
+enum DiffLine {
+    Same(String),
+    Added(String),
+    Removed(String),
+    /// Unchanged lines that are left out
+    Gap,
+}
+
+impl ObjectView {
+    pub fn open(
+        kind: ApiResource,
+        name: String,
+        namespace: String,
+        clients: ClientManager,
+        sender: MessageSender,
+    ) -> ObjectView {
+        let mut view = ObjectView {
+            id: 0,
+            kind,
+            name,
+            namespace,
+            clients,
+            sender,
+            object: None,
+            format: Format::Yaml,
+            hide_managed_fields: true,
+            edit: None,
+        };
+        view.fetch();
+        view
+    }
+
+    fn fetch(&mut self) {
+        self.id = next_stream_id();
+        self.object = None;
+        get_object(
+            self.clients.clone(),
+            self.namespace.clone(),
+            self.kind.clone(),
+            self.name.clone(),
+            self.id,
+            self.sender.clone(),
+        );
+    }
+
+    /// Shows the view in its own window, returning false once it is closed
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        let mut open = true;
+        egui::Window::new(format!("{}: {}", self.kind.kind, self.name))
+            .id(egui::Id::new("object_view"))
+            .open(&mut open)
+            .default_size([600.0, 600.0])
+            .resizable(true)
+            .show(ctx, |ui| self.contents(ui));
+        open
+    }
+
+    pub fn contents(&mut self, ui: &mut egui::Ui) {
+        let object = match &self.object {
+            Some(Ok(object)) if self.hide_managed_fields => without_managed_fields(object),
+            Some(Ok(object)) => object.clone(),
+            Some(Err(err)) => {
+                ui.colored_label(Color32::RED, err);
+                if ui.button("Retry").clicked() {
+                    self.fetch();
+                }
+                return;
+            }
+            None => {
+                ui.add(egui::widgets::Spinner::new());
+                return;
+            }
+        };
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.format, Format::Yaml, "YAML");
+            ui.selectable_value(&mut self.format, Format::Json, "JSON");
+            ui.separator();
+            ui.checkbox(&mut self.hide_managed_fields, "Hide managedFields");
+            if ui.button("Copy").clicked() {
+                ui.output_mut(|o| o.copied_text = to_text(&object, self.format));
+            }
+            if ui.button("Refresh").clicked() {
+                self.edit = None;
+                self.fetch();
+            }
+            if self.edit.is_none() && ui.button("Edit").clicked() {
+                self.edit = Some(Edit {
+                    text: to_text(&object, Format::Yaml),
+                    base: object.clone(),
+                    patch: Value::Null,
+                    force: false,
+                    stage: Stage::Editing,
+                    error: None,
+                });
+            }
+        });
+        ui.separator();
+        if self.edit.is_some() {
+            self.edit_contents(ui);
+            return;
+        }
+        let format = self.format;
+        let root = egui::Id::new(("object", self.id));
+        egui::ScrollArea::both()
+            .id_source("object_view")
+            .auto_shrink([false, false])
+            .show(ui, |ui| match &object {
+                Value::Object(map) => {
+                    for (key, value) in map.iter() {
+                        value_tree(ui, Some(key), value, format, root.with(key), 0);
+                    }
+                }
+                other => value_tree(ui, None, other, format, root, 0),
+            });
+    }
+
+    /// Editing the object as YAML, then reviewing what a dry run says it would change
+    /// before applying it for real
+    fn edit_contents(&mut self, ui: &mut egui::Ui) {
+        let edit = match self.edit.as_mut() {
+            Some(edit) => edit,
+            None => return,
+        };
+        if let Some(err) = &edit.error {
+            ui.colored_label(Color32::RED, err);
+        }
+        let mut check = None;
+        let mut apply = false;
+        let mut back = false;
+        let mut cancel = false;
+        match &edit.stage {
+            Stage::Editing => {
+                ui.horizontal(|ui| {
+                    if ui.button("Preview changes").clicked() {
+                        match parse_edit(&edit.text, &self.name) {
+                            Ok(object) => {
+                                let removed = removed_fields(&edit.base, &object);
+                                if removed.is_empty() {
+                                    check = Some((changed_fields(&edit.base, &object), false));
+                                } else {
+                                    edit.error = Some(format!(
+                                        "Applying cannot remove fields, put back {}",
+                                        removed.join(", ")
+                                    ));
+                                }
+                            }
+                            Err(err) => edit.error = Some(err),
+                        }
+                    }
+                    cancel = ui.button("Cancel").clicked();
+                });
+                egui::ScrollArea::vertical()
+                    .id_source("object_edit")
+                    .show(ui, |ui| {
+                        egui::TextEdit::multiline(&mut edit.text)
+                            .code_editor()
+                            .desired_width(f32::INFINITY)
+                            .desired_rows(30)
+                            .show(ui);
+                    });
+            }
+            Stage::Checking(_) | Stage::Applying(_) => {
+                ui.add(egui::widgets::Spinner::new());
+            }
+            Stage::Reviewing(diff) => {
+                ui.horizontal(|ui| {
+                    if !diff.is_empty() {
+                        apply = ui.button("Apply").clicked();
+                    }
+                    back = ui.button("Back to editing").clicked();
+                });
+                if edit.force {
+                    ui.label("Fields other managers own will be taken over");
+                }
+                if diff.is_empty() {
+                    ui.label("Nothing would change");
+                }
+                egui::ScrollArea::vertical()
+                    .id_source("object_diff")
+                    .show(ui, |ui| diff_view(ui, diff));
+            }
+            Stage::Conflict(message) => {
+                ui.label("Other managers own fields this edit changes:");
+                ui.colored_label(Color32::YELLOW, message);
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Force")
+                        .on_hover_text("Take the fields over")
+                        .clicked()
+                    {
+                        check = Some((edit.patch.clone(), true));
+                    }
+                    back = ui.button("Back to editing").clicked();
+                });
+            }
+        }
+        if back {
+            edit.stage = Stage::Editing;
+        }
+        let to_apply = match check {
+            Some((patch, force)) => Some((patch, force, true)),
+            None if apply => Some((edit.patch.clone(), edit.force, false)),
+            None => None,
+        };
+        if cancel {
+            self.edit = None;
+        } else if let Some((patch, force, dry_run)) = to_apply {
+            let id = self.apply(patch.clone(), force, dry_run);
+            if let Some(edit) = self.edit.as_mut() {
+                edit.patch = patch;
+                edit.force = force;
+                edit.error = None;
+                edit.stage = if dry_run {
+                    Stage::Checking(id)
+                } else {
+                    Stage::Applying(id)
+                };
+            }
+        }
+    }
+
+    fn apply(&self, patch: Value, force: bool, dry_run: bool) -> u64 {
+        let id = next_stream_id();
+        apply_object(
+            self.clients.clone(),
+            self.namespace.clone(),
+            self.kind.clone(),
+            self.name.clone(),
+            patch,
+            force,
+            dry_run,
+            id,
+            self.sender.clone(),
+        );
+        id
+    }
+
+    pub fn receive(&mut self, id: u64, object: Result<DynamicObject, String>) {
+        if id == self.id {
+            self.object = Some(object.and_then(|o| json_value(&o)));
+        }
+    }
+
+    pub fn receive_applied(&mut self, id: u64, applied: Result<Applied, String>) {
+        let edit = match self.edit.as_mut() {
+            Some(edit) => edit,
+            None => return,
+        };
+        match &edit.stage {
+            Stage::Checking(pending) | Stage::Applying(pending) if *pending == id => {}
+            _ => return,
+        }
+        let outcome = applied.and_then(|applied| match applied {
+            // Compared with the object as it is now, which may have moved on since
+            // the edit started
+            Applied::Checked { live, applied } => {
+                let live = json_value(&live)?;
+                let before = to_text(&without_managed_fields(&live), Format::Yaml);
+                let after = to_text(
+                    &without_managed_fields(&json_value(&applied)?),
+                    Format::Yaml,
+                );
+                edit.stage = Stage::Reviewing(diff_lines(&before, &after));
+                Ok(Some(live))
+            }
+            Applied::Done(applied) => Ok(Some(json_value(&applied)?)),
+            Applied::Conflict(message) => {
+                edit.stage = Stage::Conflict(message);
+                Ok(None)
+            }
+        });
+        match outcome {
+            Ok(Some(object)) => {
+                if matches!(edit.stage, Stage::Applying(_)) {
+                    self.edit = None;
+                }
+                self.object = Some(Ok(object));
+            }
+            Ok(None) => {}
+            Err(err) => {
+                edit.error = Some(err);
+                edit.stage = Stage::Editing;
+            }
+        }
+    }
+}
+
+fn diff_view(ui: &mut egui::Ui, diff: &[DiffLine]) {
+    for line in diff {
+        let (prefix, text, color) = match line {
+            DiffLine::Same(text) => (" ", text.as_str(), ui.visuals().text_color()),
+            DiffLine::Added(text) => ("+", text.as_str(), Color32::GREEN),
+            DiffLine::Removed(text) => ("-", text.as_str(), Color32::RED),
+            DiffLine::Gap => ("", "...", Color32::GRAY),
+        };
+        let mut job = LayoutJob::default();
+        append(&mut job, &format!("{} {}", prefix, text), color);
+        ui.label(job);
+    }
+}
+
+/// The lines that differ between `before` and `after`, with a little unchanged
+/// context around each change
+fn diff_lines(before: &str, after: &str) -> Vec<DiffLine> {
+    let diff = TextDiff::from_lines(before, after);
+    let mut lines = vec![];
+    for (i, group) in diff.grouped_ops(DIFF_CONTEXT).iter().enumerate() {
+        if i > 0 {
+            lines.push(DiffLine::Gap);
+        }
+        for op in group {
+            for change in diff.iter_changes(op) {
+                let text = change.value().trim_end_matches('\n').to_owned();
+                lines.push(match change.tag() {
+                    ChangeTag::Equal => DiffLine::Same(text),
+                    ChangeTag::Insert => DiffLine::Added(text),
+                    ChangeTag::Delete => DiffLine::Removed(text),
+                });
+            }
+        }
+    }
+    lines
+}
+
+/// The parts of `edited` that differ from `live`, along with what apply needs to find
+/// the object, so only the fields the user changed are applied and owned. Lists are
+/// compared and sent whole. Fields only in `live` are left as they are.
+fn changed_fields(live: &Value, edited: &Value) -> Value {
+    let mut patch = match changes(live, edited) {
+        Some(Value::Object(changed)) => changed,
+        _ => Map::new(),
+    };
+    for key in ["apiVersion", "kind"] {
+        if let Some(value) = edited.get(key) {
+            patch.insert(key.to_owned(), value.clone());
+        }
+    }
+    let mut patch = Value::Object(patch);
+    patch["metadata"]["name"] = edited
+        .pointer("/metadata/name")
+        .cloned()
+        .unwrap_or_default();
+    patch
+}
+
+fn changes(live: &Value, edited: &Value) -> Option<Value> {
+    match (live, edited) {
+        (Value::Object(live), Value::Object(edited)) => {
+            let changed: Map<String, Value> = edited
+                .iter()
+                .filter_map(|(key, value)| match live.get(key) {
+                    Some(before) => changes(before, value).map(|v| (key.clone(), v)),
+                    None => Some((key.clone(), value.clone())),
+                })
+                .collect();
+            if changed.is_empty() {
+                None
+            } else {
+                Some(Value::Object(changed))
+            }
+        }
+        _ if live == edited => None,
+        _ => Some(edited.clone()),
+    }
+}
+
+/// Paths of the map keys in `live` that `edited` leaves out, which a patch of the
+/// changed fields cannot express. What `parse_edit` drops itself does not count.
+fn removed_fields(live: &Value, edited: &Value) -> Vec<String> {
+    let mut removed = vec![];
+    removed_keys(live, edited, "", &mut removed);
+    removed.retain(|path| path != "status" && path != "metadata.managedFields");
+    removed
+}
+
+fn removed_keys(live: &Value, edited: &Value, path: &str, removed: &mut Vec<String>) {
+    if let (Value::Object(live), Value::Object(edited)) = (live, edited) {
+        for (key, before) in live.iter() {
+            let key_path = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", path, key)
+            };
+            match edited.get(key) {
+                Some(after) => removed_keys(before, after, &key_path, removed),
+                None => removed.push(key_path),
+            }
+        }
+    }
+}
+
+/// Reads the edited YAML as the object to apply. The name has to stay the same, and
+/// what only the server writes is left out.
+fn parse_edit(text: &str, name: &str) -> Result<Value, String> {
+    let mut object: Value =
+        serde_yaml::from_str(text).map_err(|e| format!("Not valid YAML: {}", e))?;
+    if !object.is_object() {
+        return Err("Expected an object".to_owned());
+    }
+    let edited_name = object
+        .pointer("/metadata/name")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if edited_name != name {
+        return Err(format!("The name has to stay {}", name));
+    }
+    if let Some(map) = object.as_object_mut() {
+        map.remove("status");
+    }
+    Ok(without_managed_fields(&object))
+}
+
+/// One key of the object. Maps and lists are collapsible sections, open for the top
+/// levels, everything else is a single line.
+fn value_tree(
+    ui: &mut egui::Ui,
+    key: Option<&str>,
+    value: &Value,
+    format: Format,
+    id: egui::Id,
+    depth: usize,
+) {
+    let children: Vec<(Option<String>, &Value)> = match value {
+        Value::Object(map) if !map.is_empty() => {
+            map.iter().map(|(k, v)| (Some(k.clone()), v)).collect()
+        }
+        Value::Array(items) if !items.is_empty() => items.iter().map(|v| (None, v)).collect(),
+        _ => {
+            let mut job = key_job(key, format);
+            append(&mut job, &scalar_text(value, format), value_color(value));
+            ui.label(job);
+            return;
+        }
+    };
+    let (open, close) = match value {
+        Value::Array(_) => ("[", "]"),
+        _ => ("{", "}"),
+    };
+    let mut header = key_job(key, format);
+    if format == Format::Json {
+        append(&mut header, open, ui.visuals().text_color());
+    }
+    let response = egui::CollapsingHeader::new(header)
+        .id_source(id)
+        .default_open(depth < 2)
+        .show(ui, |ui| {
+            for (i, (child_key, child)) in children.into_iter().enumerate() {
+                let child_id = id.with(i);
+                match child_key {
+                    Some(child_key) => {
+                        value_tree(ui, Some(&child_key), child, format, child_id, depth + 1)
+                    }
+                    None if format == Format::Yaml => {
+                        value_tree(ui, Some("-"), child, format, child_id, depth + 1)
+                    }
+                    None => value_tree(ui, None, child, format, child_id, depth + 1),
+                }
+            }
+        });
+    if format == Format::Json && response.openness > 0.0 {
+        ui.monospace(close);
+    }
+}
+
+/// `key: ` in YAML or `"key": ` in JSON. List items in YAML use `-` as their key.
+fn key_job(key: Option<&str>, format: Format) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    match (key, format) {
+        (Some("-"), Format::Yaml) => append(&mut job, "- ", KEYWORD_COLOR),
+        (Some(key), Format::Yaml) => append(&mut job, &format!("{}: ", key), KEY_COLOR),
+        (Some(key), Format::Json) => append(&mut job, &format!("{:?}: ", key), KEY_COLOR),
+        (None, _) => {}
+    }
+    job
+}
+
+fn append(job: &mut LayoutJob, text: &str, color: Color32) {
+    let format = TextFormat {
+        font_id: FontId::monospace(12.0),
+        color,
+        ..Default::default()
+    };
+    job.append(text, 0.0, format);
+}
+
+fn value_color(value: &Value) -> Color32 {
+    match value {
+        Value::String(_) => STRING_COLOR,
+        Value::Number(_) => NUMBER_COLOR,
+        _ => KEYWORD_COLOR,
+    }
+}
+
+/// A value that has no section of its own, written the way the format would
+fn scalar_text(value: &Value, format: Format) -> String {
+    match format {
+        Format::Json => serde_json::to_string(value).unwrap_or_default(),
+        Format::Yaml => serde_yaml::to_string(value)
+            .map(|text| text.trim_end().to_owned())
+            .unwrap_or_default(),
+    }
+}
+
+/// The whole object as text, for copying
+fn to_text(value: &Value, format: Format) -> String {
+    match format {
+        Format::Json => serde_json::to_string_pretty(value).unwrap_or_default(),
+        Format::Yaml => serde_yaml::to_string(value).unwrap_or_default(),
+    }
+}
+
+fn json_value(object: &DynamicObject) -> Result<Value, String> {
+    serde_json::to_value(object).map_err(|e| e.to_string())
+}
+
+/// `metadata.managedFields` is long and rarely what anyone is looking for
+fn without_managed_fields(object: &Value) -> Value {
+    let mut object = object.clone();
+    if let Some(metadata) = object.get_mut("metadata").and_then(Value::as_object_mut) {
+        metadata.remove("managedFields");
+    }
+    object
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[cfg(test)]
+    mod scalar_text {
+        use super::*;
+
+        #[test]
+        pub fn quotes_strings_that_need_it_in_yaml() {
+            assert_eq!(scalar_text(&json!("web"), Format::Yaml), "web");
+            assert_eq!(scalar_text(&json!("true"), Format::Yaml), "'true'");
+            assert_eq!(scalar_text(&json!(3), Format::Yaml), "3");
+        }
+
+        #[test]
+        pub fn writes_json_literals() {
+            assert_eq!(scalar_text(&json!("web"), Format::Json), "\"web\"");
+            assert_eq!(scalar_text(&json!(null), Format::Json), "null");
+            assert_eq!(scalar_text(&json!({}), Format::Json), "{}");
+        }
+    }
+
+    #[cfg(test)]
+    mod without_managed_fields {
+        use super::*;
+
+        #[test]
+        pub fn drops_only_managed_fields() {
+            let object = json!({"metadata": {
+                "name": "web",
+                "managedFields": [{"manager": "kubectl"}]
+            }});
+            assert_eq!(
+                without_managed_fields(&object),
+                json!({"metadata": {"name": "web"}})
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod diff_lines {
+        use super::*;
+
+        #[test]
+        pub fn shows_changes_with_context() {
+            let before = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+            let after = "a\nb\nc\nd\ne\nf\ng\nh\ni\nJ\n";
+            assert_eq!(
+                diff_lines(before, after),
+                vec![
+                    DiffLine::Same("g".to_owned()),
+                    DiffLine::Same("h".to_owned()),
+                    DiffLine::Same("i".to_owned()),
+                    DiffLine::Removed("j".to_owned()),
+                    DiffLine::Added("J".to_owned()),
+                ]
+            );
+        }
+
+        #[test]
+        pub fn marks_gaps_between_changes() {
+            let before = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+            let after = "one\n2\n3\n4\n5\n6\n7\n8\n9\nten\n";
+            let diff = diff_lines(before, after);
+            assert!(diff.contains(&DiffLine::Gap));
+            assert_eq!(diff.first(), Some(&DiffLine::Removed("1".to_owned())));
+            assert_eq!(diff.last(), Some(&DiffLine::Added("ten".to_owned())));
+        }
+
+        #[test]
+        pub fn nothing_for_the_same_text() {
+            assert!(diff_lines("a\nb\n", "a\nb\n").is_empty());
+        }
+    }
+
+    #[cfg(test)]
+    mod parse_edit {
+        use super::*;
+
+        #[test]
+        pub fn leaves_out_status_and_managed_fields() {
+            let text = "kind: Deployment
+metadata:
+  name: web
+  managedFields: []
+spec:
+  replicas: 3
+status:
+  readyReplicas: 2
+";
+            assert_eq!(
+                parse_edit(text, "web"),
+                Ok(json!({
+                    "kind": "Deployment",
+                    "metadata": {"name": "web"},
+                    "spec": {"replicas": 3}
+                }))
+            );
+        }
+
+        #[test]
+        pub fn rejects_renames() {
+            let text = "metadata:\n  name: api\n";
+            assert_eq!(
+                parse_edit(text, "web"),
+                Err("The name has to stay web".to_owned())
+            );
+        }
+
+        #[test]
+        pub fn rejects_invalid_yaml() {
+            assert!(parse_edit("metadata: [", "web")
+                .unwrap_err()
+                .starts_with("Not valid YAML"));
+        }
+    }
+
+    #[cfg(test)]
+    mod changed_fields {
+        use super::*;
+
+        fn live() -> Value {
+            json!({
+                "apiVersion": "apps/v1",
+                "kind": "Deployment",
+                "metadata": {
+                    "name": "web",
+                    "namespace": "dev",
+                    "labels": {"app": "web"},
+                    "resourceVersion": "42"
+                },
+                "spec": {
+                    "replicas": 2,
+                    "template": {"spec": {"containers": [{"name": "web", "image": "web:1"}]}}
+                }
+            })
+        }
+
+        #[test]
+        pub fn one_field_edit_is_a_one_field_patch() {
+            let mut edited = live();
+            edited["spec"]["replicas"] = json!(3);
+            assert_eq!(
+                changed_fields(&live(), &edited),
+                json!({
+                    "spec": {"replicas": 3},
+                    "apiVersion": "apps/v1",
+                    "kind": "Deployment",
+                    "metadata": {"name": "web"}
+                })
+            );
+        }
+
+        #[test]
+        pub fn keeps_changed_metadata_next_to_the_name() {
+            let mut edited = live();
+            edited["metadata"]["labels"]["tier"] = json!("front");
+            assert_eq!(
+                changed_fields(&live(), &edited)["metadata"],
+                json!({"labels": {"tier": "front"}, "name": "web"})
+            );
+        }
+
+        #[test]
+        pub fn sends_changed_lists_whole() {
+            let mut edited = live();
+            edited["spec"]["template"]["spec"]["containers"][0]["image"] = json!("web:2");
+            assert_eq!(
+                changed_fields(&live(), &edited)["spec"],
+                json!({"template": {"spec": {"containers": [{"name": "web", "image": "web:2"}]}}})
+            );
+        }
+
+        #[test]
+        pub fn only_identifies_the_object_without_changes() {
+            assert_eq!(
+                changed_fields(&live(), &live()),
+                json!({"apiVersion": "apps/v1", "kind": "Deployment", "metadata": {"name": "web"}})
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod removed_fields {
+        use super::*;
+
+        #[test]
+        pub fn finds_removed_map_keys() {
+            let live = json!({
+                "metadata": {"name": "web", "labels": {"app": "web", "tier": "front"}},
+                "spec": {"replicas": 2, "paused": false}
+            });
+            let edited = json!({
+                "metadata": {"name": "web", "labels": {"app": "web"}},
+                "spec": {"replicas": 3}
+            });
+            assert_eq!(
+                removed_fields(&live, &edited),
+                vec!["metadata.labels.tier".to_owned(), "spec.paused".to_owned()]
+            );
+        }
+
+        #[test]
+        pub fn ignores_what_the_edit_never_has() {
+            let live = json!({
+                "metadata": {"name": "web", "managedFields": []},
+                "status": {"readyReplicas": 2}
+            });
+            let edited = json!({"metadata": {"name": "web"}});
+            assert!(removed_fields(&live, &edited).is_empty());
+        }
+    }
+
+    #[cfg(test)]
+    mod to_text {
+        use super::*;
+
+        #[test]
+        pub fn writes_either_format() {
+            let object = json!({"kind": "Pod", "spec": {"replicas": 2}});
+            assert_eq!(
+                to_text(&object, Format::Yaml),
+                "kind: Pod\nspec:\n  replicas: 2\n"
+            );
+            assert_eq!(
+                to_text(&object, Format::Json),
+                "{\n  \"kind\": \"Pod\",\n  \"spec\": {\n    \"replicas\": 2\n  }\n}"
+            );
+        }
+    }
+}
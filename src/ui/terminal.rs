@@ -0,0 +1,393 @@
+use crate::kube_res::{
+    channel::MessageSender,
+    client::ClientManager,
+    exec::{open_shell, ExecEvent, ShellSession},
+    logs::next_stream_id,
+};
+use eframe::egui;
+use eframe::egui::text::{LayoutJob, LayoutSection};
+use eframe::egui::{Color32, Event, FontId, Key, Modifiers, Sense, TextFormat};
+
+// Lines kept above the screen to scroll back through
+const SCROLLBACK: usize = 1000;
+const FONT_SIZE: f32 = 13.0;
+
+struct Tab {
+    id: u64,
+    title: String,
+    parser: vt100::Parser,
+    // The size last sent to the shell, so it is told again only when it changes
+    sent_size: Option<(u16, u16)>,
+    scrollback: usize,
+    /// None once the shell has exited
+    session: Option<ShellSession>,
+    status: Option<String>,
+}
+
+/// A panel of shells into pods, one tab each
+pub struct Terminals {
+    tabs: Vec<Tab>,
+    selected: usize,
+    clients: ClientManager,
+    sender: MessageSender,
+}
+
+impl Terminals {
+    pub fn new(clients: ClientManager, sender: MessageSender) -> Terminals {
+        Terminals {
+            tabs: vec![],
+            selected: 0,
+            clients,
+            sender,
+        }
+    }
+
+    pub fn open(&mut self, namespace: String, pod: String, container: String) {
+        let id = next_stream_id();
+        let title = format!("{}/{}", pod, container);
+        let session = open_shell(
+            self.clients.clone(),
+            namespace,
+            pod,
+            container,
+            id,
+            self.sender.clone(),
+        );
+        self.tabs.push(Tab {
+            id,
+            title,
+            parser: vt100::Parser::new(24, 80, SCROLLBACK),
+            sent_size: None,
+            scrollback: 0,
+            session: Some(session),
+            status: None,
+        });
+        self.selected = self.tabs.len() - 1;
+    }
+
+    pub fn receive(&mut self, id: u64, event: Result<ExecEvent, String>) {
+        let tab = match self.tabs.iter_mut().find(|t| t.id == id) {
+            Some(tab) => tab,
+            None => return,
+        };
+        match event {
+            Ok(ExecEvent::Output(bytes)) => tab.parser.process(&bytes),
+            Ok(ExecEvent::Exited(status)) => {
+                tab.status = Some(status);
+                tab.session = None;
+            }
+            Err(err) => {
+                tab.status = Some(err);
+                tab.session = None;
+            }
+        }
+    }
+
+    /// Shows the panel while there are shells open. Closing a tab ends its shell.
+    pub fn show(&mut self, ctx: &egui::Context) {
+        if self.tabs.is_empty() {
+            return;
+        }
+        egui::TopBottomPanel::bottom("terminals")
+            .resizable(true)
+            .default_height(320.0)
+            .show(ctx, |ui| {
+                let mut closed = None;
+                ui.horizontal(|ui| {
+                    for (i, tab) in self.tabs.iter().enumerate() {
+                        let title = match tab.session {
+                            Some(_) => tab.title.clone(),
+                            None => format!("{} (exited)", tab.title),
+                        };
+                        if ui.selectable_label(i == self.selected, title).clicked() {
+                            self.selected = i;
+                        }
+                        if ui.small_button("x").on_hover_text("Close shell").clicked() {
+                            closed = Some(i);
+                        }
+                        ui.separator();
+                    }
+                    if ui.button("Close all").clicked() {
+                        self.tabs.clear();
+                    }
+                });
+                if let Some(i) = closed {
+                    // Dropping the tab drops its session, which ends the shell
+                    self.tabs.remove(i);
+                }
+                if self.tabs.is_empty() {
+                    return;
+                }
+                self.selected = self.selected.min(self.tabs.len() - 1);
+                let tab = &mut self.tabs[self.selected];
+                if let Some(status) = &tab.status {
+                    ui.colored_label(Color32::LIGHT_RED, status);
+                }
+                terminal(ui, tab);
+            });
+    }
+}
+
+fn terminal(ui: &mut egui::Ui, tab: &mut Tab) {
+    let font = FontId::monospace(FONT_SIZE);
+    let char_width = ui.fonts(|f| f.glyph_width(&font, 'M'));
+    let row_height = ui.fonts(|f| f.row_height(&font));
+    let size = ui.available_size();
+    let cols = ((size.x / char_width).floor() as u16).max(20);
+    let rows = ((size.y / row_height).floor() as u16).max(5);
+    if tab.sent_size != Some((rows, cols)) {
+        tab.parser.set_size(rows, cols);
+        if let Some(session) = &tab.session {
+            session.resize(cols, rows);
+        }
+        tab.sent_size = Some((rows, cols));
+    }
+
+    let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+    if response.clicked() {
+        response.request_focus();
+    }
+    if response.hovered() {
+        let scroll = ui.input(|i| i.scroll_delta.y);
+        let lines = (scroll / row_height).round() as isize;
+        tab.scrollback = (tab.scrollback as isize + lines).clamp(0, SCROLLBACK as isize) as usize;
+        tab.parser.set_scrollback(tab.scrollback);
+    }
+    if response.has_focus() {
+        // Keep Tab and the arrow keys in the terminal instead of moving focus
+        ui.memory_mut(|m| m.lock_focus(response.id, true));
+        let input = input_bytes(&ui.input(|i| i.events.clone()));
+        if !input.is_empty() {
+            if let Some(session) = &tab.session {
+                // Typing jumps back down to the live screen
+                tab.scrollback = 0;
+                tab.parser.set_scrollback(0);
+                session.write(input);
+            }
+        }
+    }
+
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, Color32::from_gray(16));
+    let screen = tab.parser.screen();
+    let galley = ui.fonts(|f| f.layout_job(screen_job(screen, &font)));
+    painter.galley(rect.min, galley);
+    if response.has_focus() && !screen.hide_cursor() && tab.scrollback == 0 {
+        let (row, col) = screen.cursor_position();
+        let min = rect.min + egui::vec2(col as f32 * char_width, row as f32 * row_height);
+        let cursor = egui::Rect::from_min_size(min, egui::vec2(char_width, row_height));
+        painter.rect_filled(cursor, 0.0, Color32::from_white_alpha(120));
+    }
+}
+
+/// Bytes to send the shell for what was typed or pasted
+fn input_bytes(events: &[Event]) -> Vec<u8> {
+    let mut bytes = vec![];
+    for event in events {
+        match event {
+            Event::Text(text) => bytes.extend(text.as_bytes()),
+            Event::Paste(text) => bytes.extend(text.as_bytes()),
+            Event::Key {
+                key,
+                pressed: true,
+                modifiers,
+                ..
+            } => bytes.extend(key_bytes(*key, *modifiers)),
+            _ => {}
+        }
+    }
+    bytes
+}
+
+/// Escape sequences for keys that do not come through as text
+fn key_bytes(key: Key, modifiers: Modifiers) -> Vec<u8> {
+    if modifiers.ctrl {
+        // Ctrl+V is a paste, which arrives as its own event
+        let name = key.name();
+        return match name.as_bytes() {
+            [letter @ b'A'..=b'Z'] if key != Key::V => vec![letter - b'A' + 1],
+            _ => vec![],
+        };
+    }
+    let sequence: &[u8] = match key {
+        Key::Enter => b"\r",
+        Key::Tab => b"\t",
+        Key::Backspace => b"\x7f",
+        Key::Escape => b"\x1b",
+        Key::ArrowUp => b"\x1b[A",
+        Key::ArrowDown => b"\x1b[B",
+        Key::ArrowRight => b"\x1b[C",
+        Key::ArrowLeft => b"\x1b[D",
+        Key::Home => b"\x1b[H",
+        Key::End => b"\x1b[F",
+        Key::Insert => b"\x1b[2~",
+        Key::Delete => b"\x1b[3~",
+        Key::PageUp => b"\x1b[5~",
+        Key::PageDown => b"\x1b[6~",
+        _ => b"",
+    };
+    sequence.to_vec()
+}
+
+/// Lays out the screen as text, with runs of cells that look the same sharing a section
+fn screen_job(screen: &vt100::Screen, font: &FontId) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    let (rows, cols) = screen.size();
+    for row in 0..rows {
+        for col in 0..cols {
+            let cell = match screen.cell(row, col) {
+                Some(cell) if cell.is_wide_continuation() => continue,
+                Some(cell) => cell,
+                None => continue,
+            };
+            let mut fg = color(cell.fgcolor(), Color32::LIGHT_GRAY);
+            let mut bg = color(cell.bgcolor(), Color32::TRANSPARENT);
+            if cell.inverse() {
+                std::mem::swap(&mut fg, &mut bg);
+                if fg == Color32::TRANSPARENT {
+                    fg = Color32::from_gray(16);
+                }
+            }
+            let format = TextFormat {
+                font_id: font.clone(),
+                color: fg,
+                background: bg,
+                underline: if cell.underline() {
+                    egui::Stroke::new(1.0, fg)
+                } else {
+                    egui::Stroke::NONE
+                },
+                ..Default::default()
+            };
+            let text = if cell.has_contents() {
+                cell.contents()
+            } else {
+                " ".to_owned()
+            };
+            append(&mut job, &text, format);
+        }
+        if row + 1 < rows {
+            let format = job
+                .sections
+                .last()
+                .map(|s| s.format.clone())
+                .unwrap_or_default();
+            append(&mut job, "\n", format);
+        }
+    }
+    job
+}
+
+fn append(job: &mut LayoutJob, text: &str, format: TextFormat) {
+    let start = job.text.len();
+    job.text.push_str(text);
+    match job.sections.last_mut() {
+        Some(last) if last.format == format => last.byte_range.end = job.text.len(),
+        _ => job.sections.push(LayoutSection {
+            leading_space: 0.0,
+            byte_range: start..job.text.len(),
+            format,
+        }),
+    }
+}
+
+fn color(color: vt100::Color, default: Color32) -> Color32 {
+    match color {
+        vt100::Color::Default => default,
+        vt100::Color::Idx(i) => ansi_color(i),
+        vt100::Color::Rgb(r, g, b) => Color32::from_rgb(r, g, b),
+    }
+}
+
+/// The xterm 256 colour palette
+fn ansi_color(i: u8) -> Color32 {
+    const BASE: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (205, 49, 49),
+        (13, 188, 121),
+        (229, 229, 16),
+        (36, 114, 200),
+        (188, 63, 188),
+        (17, 168, 205),
+        (229, 229, 229),
+        (102, 102, 102),
+        (241, 76, 76),
+        (35, 209, 139),
+        (245, 245, 67),
+        (59, 142, 234),
+        (214, 112, 214),
+        (41, 184, 219),
+        (255, 255, 255),
+    ];
+    match i {
+        0..=15 => {
+            let (r, g, b) = BASE[i as usize];
+            Color32::from_rgb(r, g, b)
+        }
+        16..=231 => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let i = i - 16;
+            Color32::from_rgb(level(i / 36), level((i / 6) % 6), level(i % 6))
+        }
+        _ => Color32::from_gray(8 + (i - 232) * 10),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(test)]
+    mod key_bytes {
+        use super::*;
+
+        #[test]
+        pub fn ctrl_letters_are_control_codes() {
+            assert_eq!(key_bytes(Key::C, Modifiers::CTRL), vec![3]);
+            assert_eq!(key_bytes(Key::D, Modifiers::CTRL), vec![4]);
+        }
+
+        #[test]
+        pub fn ctrl_v_is_left_to_paste() {
+            assert_eq!(key_bytes(Key::V, Modifiers::CTRL), Vec::<u8>::new());
+        }
+
+        #[test]
+        pub fn arrows_are_escape_sequences() {
+            assert_eq!(key_bytes(Key::ArrowUp, Modifiers::NONE), b"\x1b[A".to_vec());
+        }
+
+        #[test]
+        pub fn printable_keys_come_through_as_text() {
+            assert_eq!(key_bytes(Key::A, Modifiers::NONE), Vec::<u8>::new());
+        }
+    }
+
+    #[cfg(test)]
+    mod ansi_color {
+        use super::*;
+
+        #[test]
+        pub fn maps_the_colour_cube_and_greys() {
+            assert_eq!(ansi_color(16), Color32::from_rgb(0, 0, 0));
+            assert_eq!(ansi_color(231), Color32::from_rgb(255, 255, 255));
+            assert_eq!(ansi_color(232), Color32::from_gray(8));
+            assert_eq!(ansi_color(255), Color32::from_gray(238));
+        }
+    }
+
+    #[cfg(test)]
+    mod screen_job {
+        use super::*;
+
+        #[test]
+        pub fn merges_cells_with_the_same_look() {
+            let mut parser = vt100::Parser::new(2, 4, 0);
+            parser.process(b"ab\x1b[31mc");
+            let job = screen_job(parser.screen(), &FontId::monospace(FONT_SIZE));
+            assert_eq!(job.text, "abc \n    ");
+            // "ab", the red "c", then the rest of the screen
+            assert_eq!(job.sections.len(), 3);
+        }
+    }
+}
@@ -2,28 +2,71 @@ use crate::Board;
 use eframe::egui;
 use eframe::egui::InnerResponse;
 
-use crate::kube_res::{namespaces::get_namespaces, KubeMessage};
-use std::sync::mpsc::Sender;
+use crate::kube_res::{
+    channel::MessageSender,
+    client::ClientManager,
+    contexts::{get_contexts, switch_context, KubeContext, KubeContexts},
+    namespaces::get_namespaces,
+};
 pub struct Topbar {
     namespaces: Vec<String>,
     namespaces_loaded: bool,
-    sender: Sender<KubeMessage>,
+    namespaces_error: Option<String>,
+    contexts: Vec<KubeContext>,
+    selected_context: String,
+    client_error: Option<String>,
+    clients: ClientManager,
+    sender: MessageSender,
 }
 
 impl Topbar {
-    pub fn new(namespaces: Vec<String>, sender: Sender<KubeMessage>) -> Topbar {
+    pub fn new(namespaces: Vec<String>, clients: ClientManager, sender: MessageSender) -> Topbar {
         Topbar {
             namespaces,
             namespaces_loaded: false,
+            namespaces_error: None,
+            contexts: vec![],
+            selected_context: "".to_owned(),
+            client_error: None,
+            clients,
             sender,
         }
     }
 
     pub fn receive_namespaces(&mut self, namespaces: Vec<String>) {
         self.namespaces_loaded = true;
+        self.namespaces_error = None;
         self.namespaces = namespaces;
     }
 
+    pub fn receive_namespaces_error(&mut self, error: String) {
+        self.namespaces_loaded = true;
+        self.namespaces_error = Some(error);
+    }
+
+    pub fn receive_contexts(&mut self, contexts: KubeContexts) {
+        // Keep a context picked in the UI, it overrides the kubeconfig's current one
+        if self.selected_context.is_empty() {
+            self.selected_context = contexts.current.unwrap_or_default();
+        }
+        self.contexts = contexts.contexts;
+    }
+
+    /// The selected context and the cluster it points at
+    pub fn context(&self) -> (String, String) {
+        let cluster = self
+            .contexts
+            .iter()
+            .find(|c| c.name == self.selected_context)
+            .map(|c| c.cluster.clone())
+            .unwrap_or_default();
+        (self.selected_context.clone(), cluster)
+    }
+
+    pub fn receive_client_error(&mut self, error: Option<String>) {
+        self.client_error = error;
+    }
+
     pub fn display(
         &mut self,
         ui: &mut egui::Ui,
@@ -31,11 +74,50 @@ impl Topbar {
         board: &mut Board,
     ) -> InnerResponse<()> {
         ui.heading("Kubernetes Switchboard");
+        if let Some(err) = &self.client_error {
+            ui.colored_label(
+                egui::Color32::RED,
+                format!("Could not connect to kubernetes: {}", err),
+            );
+        }
         ui.horizontal(|ui| {
             let ns_label = ui.label("Namespace: ");
             ui.text_edit_singleline(selected_namespace)
                 .labelled_by(ns_label.id)
         });
+        ui.horizontal(|ui| {
+            ui.label("Context:");
+            let previous_context = self.selected_context.clone();
+            eframe::egui::ComboBox::new("contexts", "")
+                .width(200.0)
+                .selected_text(self.selected_context.clone())
+                .show_ui(ui, |ui| {
+                    for ctx in self.contexts.iter() {
+                        ui.selectable_value(
+                            &mut self.selected_context,
+                            ctx.name.clone(),
+                            ctx.name.clone(),
+                        );
+                    }
+                });
+            if let Some(ctx) = self
+                .contexts
+                .iter()
+                .find(|c| c.name == self.selected_context)
+            {
+                ui.label(format!("cluster: {}, user: {}", ctx.cluster, ctx.user));
+            }
+            if self.selected_context != previous_context {
+                self.namespaces_loaded = false;
+                self.namespaces_error = None;
+                switch_context(
+                    self.clients.clone(),
+                    self.selected_context.clone(),
+                    self.sender.clone(),
+                );
+                selected_namespace.clear();
+            }
+        });
         ui.horizontal(|ui| {
             if !self.namespaces_loaded {
                 ui.add(egui::widgets::Spinner::new());
@@ -52,14 +134,24 @@ impl Topbar {
                 });
             if ui.button("⟲").clicked() {
                 self.namespaces_loaded = false;
-                get_namespaces(self.sender.clone());
+                self.namespaces_error = None;
+                get_namespaces(self.clients.clone(), self.sender.clone());
+                get_contexts(self.sender.clone());
                 selected_namespace.clear();
             }
+            if let Some(err) = &self.namespaces_error {
+                ui.colored_label(
+                    egui::Color32::RED,
+                    format!("Could not load namespaces: {}", err),
+                );
+            }
         });
         ui.horizontal(|ui| {
             ui.label("Select a board:");
             ui.selectable_value(board, Board::Welcome, "Dashboard");
             ui.selectable_value(board, Board::Status, "Status");
+            ui.selectable_value(board, Board::Clusters, "Clusters");
+            ui.selectable_value(board, Board::Resources, "Resources");
         })
     }
 }